@@ -1,6 +1,7 @@
+use std::fmt;
 use std::mem::MaybeUninit;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy)]
 pub struct ConstArray<T: Copy, const N: usize> {
     data: MaybeUninit<[T; N]>,
     length: usize,
@@ -8,21 +9,10 @@ pub struct ConstArray<T: Copy, const N: usize> {
 
 impl<T: Copy, const N: usize> ConstArray<T, N> {
     pub const fn new(val: T) -> Self {
-        let mut ret = ConstArray {
-            data: MaybeUninit::<[T; N]>::uninit(),
+        ConstArray {
+            data: MaybeUninit::new([val; N]),
             length: 0,
-        };
-
-        // FIXME: This first call to `data_mut` is UB. We have no choice, since neither
-        // `mem::zeroed` nor `ptr::write` is a `const fn`, and we can't do `[val; N]` yet.
-        let data = ret.data_mut();
-        let mut i = 0;
-        while i < data.len() {
-            data[i] = val;
-            i += 1;
         }
-
-        ret
     }
 
     const fn data_mut(&mut self) -> &mut [T; N] {
@@ -41,30 +31,6 @@ impl<T: Copy, const N: usize> ConstArray<T, N> {
         self.length
     }
 
-    pub const fn get(&self, i: usize) -> Option<&T> {
-        if i < self.len() {
-            Some(&self.data()[i])
-        } else {
-            None
-        }
-    }
-
-    pub const fn get_mut(&mut self, i: usize) -> Option<&mut T> {
-        if i < self.len() {
-            Some(&mut self.data_mut()[i])
-        } else {
-            None
-        }
-    }
-
-    pub const fn index(&self, i: usize) -> &T {
-        expect!(self.get(i), "Index out of bounds")
-    }
-
-    pub const fn index_mut(&mut self, i: usize) -> &mut T {
-        expect!(self.get_mut(i), "Index out of bounds")
-    }
-
     pub const fn push(&mut self, val: T) {
         assert!(self.len() < N);
 
@@ -75,12 +41,12 @@ impl<T: Copy, const N: usize> ConstArray<T, N> {
 
     pub const fn insert(&mut self, index: usize, val: T) {
         assert!(self.len() < N);
-        assert!(index < self.len());
+        assert!(index <= self.len());
 
         let mut i = self.len();
         while i > index {
             self.data_mut()[i] = self.data()[i - 1];
-            i += 1;
+            i -= 1;
         }
 
         self.length += 1;
@@ -90,16 +56,8 @@ impl<T: Copy, const N: usize> ConstArray<T, N> {
     pub const fn pop(&mut self) -> T {
         assert!(self.len() > 0);
 
-        let ret = self.data()[self.length];
-        self.length -= 1;
-        ret
-    }
-
-    pub const fn swap_remove(&mut self, i: usize) {
-        assert!(i < self.len());
-
-        self.data_mut()[i] = self.data()[self.length];
         self.length -= 1;
+        self.data()[self.length]
     }
 
     pub const fn remove(&mut self, mut i: usize) {
@@ -117,14 +75,22 @@ impl<T: Copy, const N: usize> ConstArray<T, N> {
         let data = self.data().as_ptr();
         unsafe { &*std::ptr::slice_from_raw_parts(data, self.length) }
     }
+}
+
+impl<T: Copy + fmt::Debug, const N: usize> fmt::Debug for ConstArray<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
 
-    pub const fn as_slice_mut(&mut self) -> &mut [T] {
-        // FIXME: This should call `as_mut_ptr` when it is `const`.
-        let data = self.data_mut() as *mut [T] as *mut T;
-        unsafe { &mut *std::ptr::slice_from_raw_parts_mut(data, self.length) }
+impl<T: Copy + PartialEq, const N: usize> PartialEq for ConstArray<T, N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
     }
 }
 
+impl<T: Copy + Eq, const N: usize> Eq for ConstArray<T, N> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,6 +105,13 @@ mod tests {
 
             assert!(arr.len() == 3);
             assert!(arr.as_slice()[1] == 2);
+
+            arr.remove(0);
+            arr.insert(1, 4);
+            arr.insert(3, 5);
+            assert!(arr.pop() == 5);
+            assert!(arr.len() == 3);
+            assert!(arr.as_slice()[0] == 2 && arr.as_slice()[1] == 4 && arr.as_slice()[2] == 3);
         }
 
         test();
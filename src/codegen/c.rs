@@ -0,0 +1,355 @@
+use std::fmt::Write;
+
+use super::{Layout, write_rows};
+use crate::error::PhfBuildError;
+use crate::util::Key;
+
+/// Generates C source for a keyword lookup in the shape produced by gperf.
+///
+/// The output defines a `static unsigned int hash(const char *str, size_t len)` function and a
+/// `const char *in_word_set(const char *str, size_t len)` function that returns the matching
+/// keyword or a null pointer, along with the usual `TOTAL_KEYWORDS`, `MIN_WORD_LENGTH`,
+/// `MAX_WORD_LENGTH`, `MIN_HASH_VALUE` and `MAX_HASH_VALUE` macros. Like gperf's `-l` option, the
+/// length of the input is compared before its contents, so `str` need not be NUL-terminated.
+pub struct CWordSet<'a> {
+    keys: Vec<Key<'a>>,
+    ignore_case: bool,
+    keysig: Option<&'a [isize]>,
+    hash_name: &'a str,
+    lookup_name: &'a str,
+}
+
+impl<'a> CWordSet<'a> {
+    pub fn new() -> Self {
+        CWordSet {
+            keys: Vec::new(),
+            ignore_case: false,
+            keysig: None,
+            hash_name: "hash",
+            lookup_name: "in_word_set",
+        }
+    }
+
+    pub fn key<K>(&mut self, key: &'a K) -> &mut Self
+    where
+        K: AsRef<[u8]> + ?Sized,
+    {
+        self.keys.push(key.as_ref());
+        self
+    }
+
+    /// Compare keys without regard to ASCII case, like gperf's `--ignore-case`.
+    pub fn ignore_ascii_case(&mut self, yes: bool) -> &mut Self {
+        self.ignore_case = yes;
+        self
+    }
+
+    /// Uses the given keysig instead of searching for one, like gperf's `-k` option.
+    pub fn keysig(&mut self, sig: &'a [isize]) -> &mut Self {
+        self.keysig = Some(sig);
+        self
+    }
+
+    /// Sets the name of the hash function, like gperf's `--hash-function-name`.
+    pub fn hash_function_name(&mut self, name: &'a str) -> &mut Self {
+        self.hash_name = name;
+        self
+    }
+
+    /// Sets the name of the lookup function, like gperf's `--lookup-function-name`.
+    pub fn lookup_function_name(&mut self, name: &'a str) -> &mut Self {
+        self.lookup_name = name;
+        self
+    }
+
+    /// Runs the search and returns the C source code.
+    pub fn generate(&self) -> Result<String, PhfBuildError> {
+        let layout = match self.keysig {
+            Some(sig) => Layout::with_keysig(&self.keys, sig, self.ignore_case)?,
+            None => Layout::new(&self.keys, self.ignore_case)?,
+        };
+
+        let mut out = String::new();
+        self.write_source(&mut out, &layout).expect("writing to a `String` cannot fail");
+        Ok(out)
+    }
+
+    fn write_source(&self, out: &mut String, layout: &Layout) -> std::fmt::Result {
+        let min_len = self.keys.iter().map(|key| key.len()).min().unwrap_or(0);
+        let max_len = self.keys.iter().map(|key| key.len()).max().unwrap_or(0);
+        let min_hash = self.keys.iter().map(|key| layout.hash(key)).min().unwrap_or(0);
+
+        writeln!(out, "/* C code produced by const-phf. Do not edit. */")?;
+        writeln!(out)?;
+        writeln!(out, "#include <stddef.h>")?;
+        writeln!(out, "#include <string.h>")?;
+        writeln!(out)?;
+        writeln!(out, "#define TOTAL_KEYWORDS {}", self.keys.len())?;
+        writeln!(out, "#define MIN_WORD_LENGTH {}", min_len)?;
+        writeln!(out, "#define MAX_WORD_LENGTH {}", max_len)?;
+        writeln!(out, "#define MIN_HASH_VALUE {}", min_hash)?;
+        writeln!(out, "#define MAX_HASH_VALUE {}", layout.max_hash)?;
+        writeln!(out)?;
+
+        // An empty slot has length 0, so with no keys it would match an empty string.
+        if self.keys.is_empty() {
+            return write!(out, "{}", EMPTY_LOOKUP_FN.replace("{name}", self.lookup_name));
+        }
+
+        if self.ignore_case {
+            write!(out, "{}", CASE_MEMCMP_FN)?;
+            writeln!(out)?;
+        }
+
+        self.write_hash_fn(out, layout)?;
+        writeln!(out)?;
+        self.write_lookup_fn(out, layout, max_len)
+    }
+
+    fn write_hash_fn(&self, out: &mut String, layout: &Layout) -> std::fmt::Result {
+        // Characters are folded before they are hashed, so uppercase letters must have the same
+        // associated value as their lowercase counterparts.
+        let mut assoc_values = layout.assoc_values;
+        if layout.ignore_case {
+            for c in b'A'..=b'Z' {
+                assoc_values[c as usize] = assoc_values[c.to_ascii_lowercase() as usize];
+            }
+        }
+
+        let assoc_type = if assoc_values.iter().all(|&v| v <= u8::MAX as u16) {
+            "unsigned char"
+        } else {
+            "unsigned short"
+        };
+
+        writeln!(out, "static unsigned int")?;
+        writeln!(out, "{} (register const char *str, register size_t len)", self.hash_name)?;
+        writeln!(out, "{{")?;
+
+        // Keys told apart by length alone don't read any characters.
+        if layout.sig.is_empty() {
+            writeln!(out, "  (void)str;")?;
+            writeln!(out, "  return len;")?;
+            return writeln!(out, "}}");
+        }
+
+        writeln!(out, "  static const {} asso_values[] =", assoc_type)?;
+        writeln!(out, "    {{")?;
+        write_rows(out, "      ", assoc_values.iter().map(|v| v.to_string()))?;
+        writeln!(out, "    }};")?;
+        writeln!(out, "  register unsigned int hval = len;")?;
+        writeln!(out)?;
+
+        for &idx in &layout.sig {
+            let abs_idx = idx.unsigned_abs();
+            let c = if idx < 0 {
+                format!("str[len - {}]", abs_idx)
+            } else {
+                format!("str[{}]", abs_idx)
+            };
+
+            writeln!(out, "  if (len > {})", abs_idx)?;
+            writeln!(out, "    hval += asso_values[(unsigned char){}];", c)?;
+        }
+
+        writeln!(out, "  return hval;")?;
+        writeln!(out, "}}")
+    }
+
+    fn write_lookup_fn(
+        &self,
+        out: &mut String,
+        layout: &Layout,
+        max_len: usize,
+    ) -> std::fmt::Result {
+        let len_type = if max_len <= u8::MAX as usize {
+            "unsigned char"
+        } else {
+            "unsigned int"
+        };
+
+        let memcmp = if self.ignore_case { "gperf_case_memcmp" } else { "memcmp" };
+
+        writeln!(out, "const char *")?;
+        writeln!(out, "{} (register const char *str, register size_t len)", self.lookup_name)?;
+        writeln!(out, "{{")?;
+
+        writeln!(out, "  static const {} lengthtable[] =", len_type)?;
+        writeln!(out, "    {{")?;
+        let lens = layout.table.iter().map(|slot| match slot {
+            Some(idx) => self.keys[*idx].len().to_string(),
+            None => "0".to_owned(),
+        });
+        write_rows(out, "      ", lens)?;
+        writeln!(out, "    }};")?;
+
+        writeln!(out, "  static const char * const wordlist[] =")?;
+        writeln!(out, "    {{")?;
+        for slot in &layout.table {
+            match slot {
+                Some(idx) => writeln!(out, "      \"{}\",", escape_key(self.keys[*idx]))?,
+                None => writeln!(out, "      \"\",")?,
+            }
+        }
+        writeln!(out, "    }};")?;
+        writeln!(out)?;
+
+        writeln!(out, "  if (len <= MAX_WORD_LENGTH && len >= MIN_WORD_LENGTH)")?;
+        writeln!(out, "    {{")?;
+        writeln!(out, "      register unsigned int key = {} (str, len);", self.hash_name)?;
+        writeln!(out)?;
+        writeln!(out, "      if (key <= MAX_HASH_VALUE && len == lengthtable[key])")?;
+        writeln!(out, "        {{")?;
+        writeln!(out, "          register const char *s = wordlist[key];")?;
+        writeln!(out)?;
+        writeln!(out, "          if ({} (str, s, len) == 0)", memcmp)?;
+        writeln!(out, "            return s;")?;
+        writeln!(out, "        }}")?;
+        writeln!(out, "    }}")?;
+        writeln!(out, "  return 0;")?;
+        writeln!(out, "}}")
+    }
+}
+
+impl Default for CWordSet<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const CASE_MEMCMP_FN: &str = "\
+static int
+gperf_case_memcmp (register const char *s1, register const char *s2, register size_t n)
+{
+  for (; n > 0; n--)
+    {
+      unsigned char c1 = (unsigned char)*s1++;
+      unsigned char c2 = (unsigned char)*s2++;
+      if (c1 >= 'A' && c1 <= 'Z')
+        c1 += 'a' - 'A';
+      if (c2 >= 'A' && c2 <= 'Z')
+        c2 += 'a' - 'A';
+      if (c1 != c2)
+        return (int)c1 - (int)c2;
+    }
+  return 0;
+}
+";
+
+const EMPTY_LOOKUP_FN: &str = "\
+const char *
+{name} (register const char *str, register size_t len)
+{
+  (void)str;
+  (void)len;
+  return 0;
+}
+";
+
+/// Writes `key` as the contents of a C string literal.
+///
+/// Octal escapes are used for anything that isn't printable ASCII, since unlike hex escapes they
+/// can't swallow the characters that follow.
+fn escape_key(key: Key<'_>) -> String {
+    let mut ret = String::new();
+    for &c in key {
+        match c {
+            b'"' | b'\\' | b'?' => {
+                ret.push('\\');
+                ret.push(c as char);
+            }
+
+            b' '..=b'~' => ret.push(c as char),
+            _ => ret.push_str(&format!("\\{:03o}", c)),
+        }
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate() {
+        let mut set = CWordSet::new();
+        set.key("if").key("else").key("while").key("tab\t").lookup_function_name("is_keyword");
+
+        let code = set.generate().unwrap();
+        let layout = Layout::new(&[b"if", b"else", b"while", b"tab\t"], false).unwrap();
+
+        assert!(code.contains("#define TOTAL_KEYWORDS 4\n"));
+        assert!(code.contains("#define MIN_WORD_LENGTH 2\n"));
+        assert!(code.contains("#define MAX_WORD_LENGTH 5\n"));
+        assert!(code.contains(&format!("#define MAX_HASH_VALUE {}\n", layout.max_hash)));
+        assert!(code.contains("hash (register const char *str, register size_t len)"));
+        assert!(code.contains("is_keyword (register const char *str, register size_t len)"));
+        assert!(code.contains("      \"tab\\011\",\n"));
+        assert!(!code.contains("gperf_case_memcmp"));
+    }
+
+    /// Compiles `code` along with a `main` that prints `1` for each of `words` that `lookup` finds
+    /// and `0` for each it doesn't, then runs it and returns what it printed. Returns `None` if
+    /// there is no C compiler.
+    fn run_c(code: &str, lookup: &str, words: &[&str]) -> Option<String> {
+        use std::process::Command;
+
+        if Command::new("cc").arg("--version").output().is_err() {
+            return None;
+        }
+
+        let main = format!(
+            "#include <stdio.h>\n\
+             int main (int argc, char **argv)\n\
+             {{\n\
+             \x20 for (int i = 1; i < argc; i++)\n\
+             \x20   putchar ({} (argv[i], strlen (argv[i])) ? '1' : '0');\n\
+             \x20 return 0;\n\
+             }}\n",
+            lookup,
+        );
+
+        let dir = std::env::temp_dir().join(format!("const-phf-c-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src = dir.join(format!("{}.c", lookup));
+        let exe = dir.join(lookup);
+        std::fs::write(&src, format!("{}\n{}", code, main)).unwrap();
+
+        let status = Command::new("cc")
+            .args(["-std=c99", "-Wall", "-Werror", "-o"])
+            .arg(&exe)
+            .arg(&src)
+            .status()
+            .unwrap();
+        assert!(status.success(), "failed to compile {}", src.display());
+
+        let output = Command::new(&exe).args(words).output().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        Some(String::from_utf8(output.stdout).unwrap())
+    }
+
+    #[test]
+    fn compile() {
+        let mut set = CWordSet::new();
+        set.key("if").key("else").key("while").key("tab\t").lookup_function_name("is_keyword");
+
+        let words = ["if", "else", "while", "tab\t", "", "i", "els", "tab", "whilst"];
+        if let Some(found) = run_c(&set.generate().unwrap(), "is_keyword", &words) {
+            assert_eq!(found, "111100000");
+        }
+
+        let mut set = CWordSet::new();
+        set.key("select").key("from").key("where").ignore_ascii_case(true);
+
+        let words = ["select", "FROM", "Where", "join", "selects", ""];
+        if let Some(found) = run_c(&set.generate().unwrap(), "in_word_set", &words) {
+            assert_eq!(found, "111000");
+        }
+
+        let set = CWordSet::new();
+        if let Some(found) = run_c(&set.generate().unwrap(), "in_word_set", &["", "a"]) {
+            assert_eq!(found, "00");
+        }
+    }
+}
@@ -0,0 +1,611 @@
+use std::fmt;
+
+use super::{CWordSet, Layout, RustMap, SIG_CAPACITY};
+use crate::error::PhfBuildError;
+use crate::sig::{Sig, parse_key_positions};
+use crate::util::Key;
+
+/// A keyword file in the input format of gperf.
+///
+/// Of the declarations gperf accepts, `%ignore-case`, `%delimiters`, `%define
+/// hash-function-name` and `%define lookup-function-name` are honored. The rest only change the
+/// shape of gperf's own output, so they are ignored. Key positions are given on gperf's command
+/// line, not in the file, so they are set separately with `set_key_positions`.
+///
+/// ```
+/// use const_phf::codegen::GperfFile;
+///
+/// let mut file = GperfFile::parse("\
+/// struct keyword { const char *name; int token; };
+/// %%
+/// if, TOK_IF
+/// else, TOK_ELSE
+/// while, TOK_WHILE
+/// %%
+/// ").unwrap();
+///
+/// file.set_key_positions("1,$").unwrap();
+/// let code = file.to_rust("keywords", "u32").unwrap();
+/// assert!(code.contains("(b\"else\", TOK_ELSE),"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct GperfFile {
+    /// The C code between `%{` and `%}` in the declarations section.
+    pub code: String,
+
+    /// The struct declaration in the declarations section, if there is one.
+    pub struct_decl: Option<String>,
+
+    /// The keywords, in the order they appear.
+    pub keywords: Vec<GperfKeyword>,
+
+    /// The C code after the second `%%`.
+    pub functions: String,
+
+    pub ignore_case: bool,
+    pub hash_function_name: String,
+    pub lookup_function_name: String,
+
+    /// The keysig set by `set_key_positions`. If this is `None`, one is searched for.
+    pub key_positions: Option<Vec<isize>>,
+}
+
+/// A line in the keywords section of a gperf file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GperfKeyword {
+    pub key: Vec<u8>,
+
+    /// The remaining fields of the struct initializer, as C source.
+    pub fields: Vec<String>,
+
+    /// The line number of the keyword, counting from 1.
+    pub line: usize,
+}
+
+/// The reason a gperf file could not be read. Lines are counted from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GperfError {
+    /// A `%{` was never followed by `%}`.
+    UnterminatedCode { line: usize },
+
+    /// A quoted keyword was missing its closing quote or contained an invalid escape.
+    InvalidString { line: usize },
+
+    /// A keyword was followed by something other than a delimiter.
+    ExpectedDelimiter { line: usize },
+
+    /// A field of a keyword has no equivalent Rust literal.
+    InvalidValue { line: usize },
+
+    /// The keywords have no perfect hash.
+    Build(PhfBuildError),
+}
+
+impl From<PhfBuildError> for GperfError {
+    fn from(e: PhfBuildError) -> Self {
+        GperfError::Build(e)
+    }
+}
+
+impl fmt::Display for GperfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            GperfError::UnterminatedCode { line } => write!(f, "line {}: unterminated `%{{`", line),
+            GperfError::InvalidString { line } => write!(f, "line {}: invalid string", line),
+            GperfError::ExpectedDelimiter { line } => {
+                write!(f, "line {}: expected a delimiter after the keyword", line)
+            }
+
+            GperfError::InvalidValue { line } => {
+                write!(f, "line {}: value is not a literal or an identifier", line)
+            }
+
+            GperfError::Build(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for GperfError {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Declarations,
+    Keywords,
+    Functions,
+}
+
+impl GperfFile {
+    pub fn parse(src: &str) -> Result<Self, GperfError> {
+        let mut file = GperfFile {
+            code: String::new(),
+            struct_decl: None,
+            keywords: Vec::new(),
+            functions: String::new(),
+            ignore_case: false,
+            hash_function_name: "hash".to_owned(),
+            lookup_function_name: "in_word_set".to_owned(),
+            key_positions: None,
+        };
+
+        // Like gperf, treat a file with no `%%` as nothing but keywords.
+        let mut section = if src.lines().any(|line| line == "%%") {
+            Section::Declarations
+        } else {
+            Section::Keywords
+        };
+
+        let mut delimiters = ",".to_owned();
+        let mut code_start = None;
+
+        for (i, line) in src.lines().enumerate() {
+            let line_num = i + 1;
+
+            if code_start.is_some() {
+                if line.trim_end() == "%}" {
+                    code_start = None;
+                } else {
+                    file.code.push_str(line);
+                    file.code.push('\n');
+                }
+
+                continue;
+            }
+
+            if line == "%%" && section != Section::Functions {
+                section = match section {
+                    Section::Declarations => Section::Keywords,
+                    _ => Section::Functions,
+                };
+
+                continue;
+            }
+
+            match section {
+                Section::Declarations => {
+                    if line.trim_end() == "%{" {
+                        code_start = Some(line_num);
+                    } else if let Some(option) = line.strip_prefix('%') {
+                        file.parse_option(option.trim(), &mut delimiters);
+                    } else if !line.trim().is_empty() {
+                        let decl = file.struct_decl.get_or_insert_with(String::new);
+                        decl.push_str(line);
+                        decl.push('\n');
+                    }
+                }
+
+                Section::Keywords => {
+                    if line.trim().is_empty() || line.starts_with('#') {
+                        continue;
+                    }
+
+                    file.keywords.push(parse_keyword(line, line_num, &delimiters)?);
+                }
+
+                Section::Functions => {
+                    file.functions.push_str(line);
+                    file.functions.push('\n');
+                }
+            }
+        }
+
+        if let Some(line) = code_start {
+            return Err(GperfError::UnterminatedCode { line });
+        }
+
+        Ok(file)
+    }
+
+    fn parse_option(&mut self, option: &str, delimiters: &mut String) {
+        if option == "ignore-case" {
+            self.ignore_case = true;
+        } else if let Some(delims) = option.strip_prefix("delimiters=") {
+            *delimiters = delims.to_owned();
+        } else if let Some(define) = option.strip_prefix("define ") {
+            let mut words = define.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("hash-function-name"), Some(name)) => {
+                    self.hash_function_name = name.to_owned()
+                }
+
+                (Some("lookup-function-name"), Some(name)) => {
+                    self.lookup_function_name = name.to_owned()
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    /// Sets the keysig from the argument to gperf's `-k` option, such as `1,3,$`.
+    pub fn set_key_positions(&mut self, spec: &str) -> Result<(), PhfBuildError> {
+        let max_key_len = self.keywords.iter().map(|kw| kw.key.len()).max().unwrap_or(0);
+        let sig: Sig<SIG_CAPACITY> = parse_key_positions(spec.as_bytes(), max_key_len)?;
+        self.key_positions = Some(sig.as_slice().to_vec());
+        Ok(())
+    }
+
+    /// Runs the search for the keywords in this file.
+    pub fn layout(&self) -> Result<Layout, PhfBuildError> {
+        let keys = self.keys();
+        match self.key_positions {
+            Some(ref sig) => Layout::with_keysig(&keys, sig, self.ignore_case),
+            None => Layout::new(&keys, self.ignore_case),
+        }
+    }
+
+    /// Generates a Rust module with `RustMap`.
+    ///
+    /// The value of each keyword is its remaining fields: a single field is used as is, several
+    /// become a tuple, and none become `()`. Each field must be an integer, character or string
+    /// literal, which is translated to the Rust literal with the same value, or the name of a
+    /// constant. Anything else, such as a nested initializer, is an error.
+    pub fn to_rust(&self, name: &str, value_type: &str) -> Result<String, GperfError> {
+        let values = self
+            .keywords
+            .iter()
+            .map(GperfKeyword::rust_value)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut map = RustMap::new(name, value_type);
+        map.ignore_ascii_case(self.ignore_case);
+        if let Some(ref sig) = self.key_positions {
+            map.keysig(sig);
+        }
+
+        for (kw, value) in self.keywords.iter().zip(&values) {
+            map.entry(&kw.key, value);
+        }
+
+        Ok(map.generate()?)
+    }
+
+    /// Generates C code with `CWordSet`.
+    ///
+    /// Only the keywords are kept. The struct declaration and the fields of each keyword are not
+    /// part of the output.
+    pub fn to_c(&self) -> Result<String, PhfBuildError> {
+        let mut set = CWordSet::new();
+        set.ignore_ascii_case(self.ignore_case)
+            .hash_function_name(&self.hash_function_name)
+            .lookup_function_name(&self.lookup_function_name);
+        if let Some(ref sig) = self.key_positions {
+            set.keysig(sig);
+        }
+
+        for kw in &self.keywords {
+            set.key(&kw.key);
+        }
+
+        set.generate()
+    }
+
+    fn keys(&self) -> Vec<Key<'_>> {
+        self.keywords.iter().map(|kw| kw.key.as_slice()).collect()
+    }
+}
+
+impl GperfKeyword {
+    /// The remaining fields as a Rust expression, as described in `GperfFile::to_rust`.
+    pub fn rust_value(&self) -> Result<String, GperfError> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| rust_literal(field).ok_or(GperfError::InvalidValue { line: self.line }))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(match fields.len() {
+            1 => fields.into_iter().next().unwrap(),
+            _ => format!("({})", fields.join(", ")),
+        })
+    }
+}
+
+/// Translates a C literal or identifier to Rust. Integers keep their radix but lose their suffix,
+/// characters become byte literals and strings must be valid UTF-8.
+fn rust_literal(field: &str) -> Option<String> {
+    let bytes = field.as_bytes();
+    match *bytes.first()? {
+        b'"' => match parse_quoted(bytes, b'"')? {
+            (s, end) if end == bytes.len() => Some(format!("{:?}", String::from_utf8(s).ok()?)),
+            _ => None,
+        },
+
+        b'\'' => match parse_quoted(bytes, b'\'')? {
+            (c, end) if end == bytes.len() && c.len() == 1 => {
+                Some(format!("b'{}'", std::ascii::escape_default(c[0])))
+            }
+
+            _ => None,
+        },
+
+        b'-' => Some(format!("-{}", rust_integer(field[1..].trim_start())?)),
+        b'0'..=b'9' => rust_integer(field),
+        _ => {
+            let is_ident = |s: &str| {
+                s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && s.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+            };
+
+            field.split("::").all(is_ident).then(|| field.to_owned())
+        }
+    }
+}
+
+fn rust_integer(s: &str) -> Option<String> {
+    let s = s.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (prefix, digits, radix) = if let Some(hex) = s.strip_prefix("0x").or(s.strip_prefix("0X")) {
+        ("0x", hex, 16)
+    } else if s.len() > 1 && s.starts_with('0') {
+        ("0o", &s[1..], 8)
+    } else {
+        ("", s, 10)
+    };
+
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+
+    Some(format!("{}{}", prefix, digits))
+}
+
+fn parse_keyword(
+    line: &str,
+    line_num: usize,
+    delimiters: &str,
+) -> Result<GperfKeyword, GperfError> {
+    let bytes = line.as_bytes();
+    let is_delimiter = |c: u8| delimiters.as_bytes().contains(&c);
+
+    let (key, mut i) = if bytes[0] == b'"' {
+        match parse_quoted(bytes, b'"') {
+            Some(x) => x,
+            None => return Err(GperfError::InvalidString { line: line_num }),
+        }
+    } else {
+        let end = bytes.iter().position(|&c| is_delimiter(c)).unwrap_or(bytes.len());
+        (bytes[..end].to_vec(), end)
+    };
+
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+
+    let mut fields = Vec::new();
+    if i < bytes.len() {
+        if !is_delimiter(bytes[i]) {
+            return Err(GperfError::ExpectedDelimiter { line: line_num });
+        }
+
+        fields = split_fields(&line[i + 1..], is_delimiter);
+    }
+
+    Ok(GperfKeyword { key, fields, line: line_num })
+}
+
+/// Parses the C string or character literal at the start of `s`, which is delimited by `quote`,
+/// returning its contents and the offset of the byte after the closing quote.
+fn parse_quoted(s: &[u8], quote: u8) -> Option<(Vec<u8>, usize)> {
+    let mut ret = Vec::new();
+    let mut i = 1;
+    loop {
+        match *s.get(i)? {
+            c if c == quote => return Some((ret, i + 1)),
+
+            b'\\' => {
+                i += 1;
+                let c = *s.get(i)?;
+                i += 1;
+
+                let escaped = match c {
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    b'r' => b'\r',
+                    b'a' => 0x07,
+                    b'b' => 0x08,
+                    b'f' => 0x0c,
+                    b'v' => 0x0b,
+
+                    b'0'..=b'7' => {
+                        let mut n = (c - b'0') as u32;
+                        let mut digits = 1;
+                        while digits < 3 && matches!(s.get(i), Some(b'0'..=b'7')) {
+                            n = n * 8 + (s[i] - b'0') as u32;
+                            i += 1;
+                            digits += 1;
+                        }
+
+                        if n > u8::MAX as u32 {
+                            return None;
+                        }
+
+                        n as u8
+                    }
+
+                    b'x' => {
+                        let start = i;
+                        let mut n = 0u32;
+                        while let Some(d) = s.get(i).and_then(|&d| (d as char).to_digit(16)) {
+                            n = n * 16 + d;
+                            if n > u8::MAX as u32 {
+                                return None;
+                            }
+
+                            i += 1;
+                        }
+
+                        if i == start {
+                            return None;
+                        }
+
+                        n as u8
+                    }
+
+                    _ => c,
+                };
+
+                ret.push(escaped);
+            }
+
+            c => {
+                ret.push(c);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Splits the fields of a struct initializer, ignoring delimiters inside literals and brackets.
+fn split_fields(s: &str, is_delimiter: impl Fn(u8) -> bool) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in s.bytes().enumerate() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == b'\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+
+            continue;
+        }
+
+        match c {
+            b'"' | b'\'' => quote = Some(c),
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && is_delimiter(c) => {
+                fields.push(s[start..i].trim().to_owned());
+                start = i + 1;
+            }
+
+            _ => {}
+        }
+    }
+
+    fields.push(s[start..].trim().to_owned());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYWORDS: &str = r#"%{
+#include "tokens.h"
+%}
+%struct-type
+%ignore-case
+%define lookup-function-name is_reserved_word
+struct keyword { const char *name; int token; const char *desc; };
+%%
+# Comments and blank lines are skipped.
+if, TOK_IF, "if, then"
+
+"else", TOK_ELSE, "else"
+"tab\t", TOK_TAB, NULL
+while,TOK_WHILE,{ 1, 2 }
+%%
+int unused;
+"#;
+
+    #[test]
+    fn parse() {
+        let file = GperfFile::parse(KEYWORDS).unwrap();
+
+        assert_eq!(file.code, "#include \"tokens.h\"\n");
+        assert_eq!(
+            file.struct_decl.as_deref(),
+            Some("struct keyword { const char *name; int token; const char *desc; };\n"),
+        );
+        assert_eq!(file.functions, "int unused;\n");
+        assert!(file.ignore_case);
+        assert_eq!(file.hash_function_name, "hash");
+        assert_eq!(file.lookup_function_name, "is_reserved_word");
+
+        let keys: Vec<&[u8]> = file.keywords.iter().map(|kw| kw.key.as_slice()).collect();
+        assert_eq!(keys, [&b"if"[..], b"else", b"tab\t", b"while"]);
+        assert_eq!(file.keywords[0].fields, ["TOK_IF", "\"if, then\""]);
+        assert_eq!(file.keywords[0].line, 10);
+        assert_eq!(file.keywords[3].fields, ["TOK_WHILE", "{ 1, 2 }"]);
+
+        let layout = file.layout().unwrap();
+        assert_eq!(layout.len(), 4);
+        assert!(layout.ignore_case);
+    }
+
+    #[test]
+    fn key_positions() {
+        let mut file = GperfFile::parse("alpha\nbeta\ngamma\ndelta\n").unwrap();
+        assert!(file.keywords.iter().all(|kw| kw.fields.is_empty()));
+
+        file.set_key_positions("1,$").unwrap();
+        assert_eq!(file.key_positions.as_deref(), Some(&[0, -1][..]));
+        assert_eq!(file.layout().unwrap().sig, [0, -1]);
+
+        let code = file.to_rust("greek", "()").unwrap();
+        assert!(code.contains("(b\"beta\", ()),"));
+        assert!(file.to_c().unwrap().contains("in_word_set (register const char *str"));
+
+        assert_eq!(
+            file.set_key_positions("1,x"),
+            Err(PhfBuildError::InvalidKeyPositions { offset: 2 }),
+        );
+    }
+
+    #[test]
+    fn rust_values() {
+        let file = GperfFile::parse(KEYWORDS).unwrap();
+        assert_eq!(file.keywords[0].rust_value().unwrap(), "(TOK_IF, \"if, then\")");
+        assert_eq!(file.keywords[2].rust_value().unwrap(), "(TOK_TAB, NULL)");
+        assert_eq!(
+            file.to_rust("keywords", "(u32, &str)").unwrap_err(),
+            GperfError::InvalidValue { line: 14 },
+        );
+
+        let file = GperfFile::parse(
+            "%%\n\
+             a, 42\n\
+             b, 0x1Fu, 017L, 0, - 3\n\
+             c, '\\n', '\\'', \"\\t\\\"\\101\"\n\
+             d, Token::D\n",
+        )
+        .unwrap();
+
+        let values: Vec<String> = file.keywords.iter().map(|kw| kw.rust_value().unwrap()).collect();
+        assert_eq!(values, ["42", "(0x1F, 0o17, 0, -3)", r#"(b'\n', b'\'', "\t\"A")"#, "Token::D"]);
+
+        let invalid = ["1 + 2", "0x", "08", "'ab'", "\"\\xff\"", "\"a\" \"b\"", "f(x)", "a::", ""];
+        for field in invalid {
+            let file = GperfFile::parse(&format!("%%\nkey, {}\n", field)).unwrap();
+            assert_eq!(file.keywords[0].rust_value(), Err(GperfError::InvalidValue { line: 2 }));
+        }
+
+        let file = GperfFile::parse("%%\na, 1\na, 2\n").unwrap();
+        assert_eq!(
+            file.to_rust("dup", "u32"),
+            Err(GperfError::Build(PhfBuildError::DuplicateKeys { first: 0, second: 1 })),
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            GperfFile::parse("%{\nint x;\n%%\n").unwrap_err(),
+            GperfError::UnterminatedCode { line: 1 },
+        );
+        assert_eq!(
+            GperfFile::parse("%%\n\"abc, 1\n").unwrap_err(),
+            GperfError::InvalidString { line: 2 },
+        );
+        assert_eq!(
+            GperfFile::parse("%%\n\"abc\" 1\n").unwrap_err(),
+            GperfError::ExpectedDelimiter { line: 2 },
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Source code generation for perfect hashes.
+//!
+//! The generators in this module run the same search as `PhfMap::new`, but at run time, and write
+//! out the resulting hash function and table as literals. Use them from a `build.rs` to get a
+//! perfect hash without doing the search during const evaluation. Only `Strategy::Keysig` hash
+//! functions can be generated.
+
+mod c;
+mod gperf;
+mod rust;
+
+pub use c::CWordSet;
+pub use gperf::{GperfError, GperfFile, GperfKeyword};
+pub use rust::RustMap;
+
+use crate::error::PhfBuildError;
+use crate::util::{Entries, Key};
+use crate::{MAX_SMALL_KEYS, PhfConfig, PhfMap, SEARCH_TABLE_LEN, Strategy};
+
+/// The most keysig positions a generated hash function may use.
+///
+/// Generated code has no fixed limit, and the search never finds a keysig this long, so this only
+/// bounds the positions that can be given explicitly.
+const SIG_CAPACITY: usize = 64;
+
+type SearchMap<'a> = PhfMap<'a, (), SEARCH_TABLE_LEN, SIG_CAPACITY>;
+
+/// The hash function and table found for a set of keys.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    /// The positions of the characters that contribute to the hash. Negative positions count
+    /// from the end of the key.
+    pub sig: Vec<isize>,
+
+    /// The value added to the hash for each character in the keysig.
+    pub assoc_values: [u16; 256],
+
+    /// The largest hash of any key. Keys that hash higher are not in the table.
+    pub max_hash: usize,
+
+    /// The index of the key with each hash, or `None` if no key has that hash.
+    pub table: Vec<Option<usize>>,
+
+    /// Whether characters are folded to lowercase before hashing and comparison.
+    pub ignore_case: bool,
+}
+
+impl Layout {
+    pub fn new(keys: &[Key<'_>], ignore_case: bool) -> Result<Self, PhfBuildError> {
+        let kvs = Entries::Keys { keys, value: &() };
+        let map = SearchMap::build(kvs, 0, &PhfConfig::new().ignore_ascii_case(ignore_case))?;
+        Ok(Self::from_map(&map))
+    }
+
+    /// Like `new`, but uses the given keysig instead of searching for one.
+    pub fn with_keysig(
+        keys: &[Key<'_>],
+        sig: &[isize],
+        ignore_case: bool,
+    ) -> Result<Self, PhfBuildError> {
+        let kvs = Entries::Keys { keys, value: &() };
+        let config = PhfConfig::new().ignore_ascii_case(ignore_case);
+        let map = SearchMap::build_with_keysig(kvs, sig, &config)?;
+        Ok(Self::from_map(&map))
+    }
+
+    fn from_map(map: &SearchMap<'_>) -> Self {
+        // A displaced hash has no keysig or associated values to write out.
+        assert_eq!(map.strategy, Strategy::Keysig, "only keysig hashes can be generated");
+
+        Layout {
+            sig: map.sig.as_slice().to_vec(),
+            assoc_values: std::array::from_fn(|c| map.assoc_value(c as u8)),
+            max_hash: map.max_hash,
+            table: (0..=map.max_hash).map(|hash| map.slot(hash)).collect(),
+            ignore_case: map.ignore_case,
+        }
+    }
+
+    /// Returns the number of keys in the table.
+    pub fn len(&self) -> usize {
+        self.table.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the fraction of table slots that hold a key.
+    pub fn fill_ratio(&self) -> f64 {
+        self.len() as f64 / self.table.len() as f64
+    }
+
+    /// Returns `true` if table slots must be wider than a byte.
+    pub fn has_wide_slots(&self) -> bool {
+        self.len() > MAX_SMALL_KEYS
+    }
+
+    /// Returns the characters of `key` that contribute to its hash.
+    pub fn keysig(&self, key: Key<'_>) -> Vec<u8> {
+        self.sig
+            .iter()
+            .filter_map(|&idx| crate::sig::index(key, idx, self.ignore_case))
+            .collect()
+    }
+
+    pub fn hash(&self, key: Key<'_>) -> usize {
+        let mut hash = key.len();
+        for &idx in &self.sig {
+            if let Some(c) = crate::sig::index(key, idx, self.ignore_case) {
+                hash += self.assoc_values[c as usize] as usize;
+            }
+        }
+
+        hash
+    }
+}
+
+/// Writes comma-separated items, several to a line, at the given indentation.
+fn write_rows(
+    out: &mut String,
+    indent: &str,
+    items: impl Iterator<Item = String>,
+) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    const PER_ROW: usize = 16;
+
+    let items: Vec<String> = items.collect();
+    for row in items.chunks(PER_ROW) {
+        writeln!(out, "{}{},", indent, row.join(", "))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use std::fmt::Write;
+
+use super::{Layout, write_rows};
+use crate::error::PhfBuildError;
+use crate::util::Key;
+
+/// Generates a Rust module containing a perfect hash map.
+///
+/// The generated module has no dependencies, so it can be `include!`d by a crate that only uses
+/// `const-phf` as a build dependency:
+///
+/// ```
+/// let mut map = const_phf::codegen::RustMap::new("keywords", "u32");
+/// map.entry("fn", "1").entry("let", "2");
+///
+/// let code = map.generate().unwrap();
+/// assert!(code.contains("pub mod keywords {"));
+/// assert!(code.contains("pub fn get(key: &[u8]) -> Option<&'static u32> {"));
+/// ```
+///
+/// A `build.rs` writes `code` to a file in `OUT_DIR`, and the crate includes it with
+/// `include!(concat!(env!("OUT_DIR"), "/keywords.rs"))`, after which `keywords::get(b"let")` is
+/// `Some(&2)`.
+///
+/// The module contains `ENTRIES`, a `static` array of key-value pairs in the order they were
+/// added, as well as `get` and `get_index` functions with the same meaning as they have on
+/// `PhfMap`.
+pub struct RustMap<'a> {
+    name: &'a str,
+    value_type: &'a str,
+    entries: Vec<(Key<'a>, &'a str)>,
+    ignore_case: bool,
+    keysig: Option<&'a [isize]>,
+}
+
+impl<'a> RustMap<'a> {
+    /// `name` is the name of the generated module, and `value_type` is the type of its values.
+    pub fn new(name: &'a str, value_type: &'a str) -> Self {
+        RustMap {
+            name,
+            value_type,
+            entries: Vec::new(),
+            ignore_case: false,
+            keysig: None,
+        }
+    }
+
+    /// Adds a key to the map. `value` is a Rust expression of the map's value type.
+    pub fn entry<K>(&mut self, key: &'a K, value: &'a str) -> &mut Self
+    where
+        K: AsRef<[u8]> + ?Sized,
+    {
+        self.entries.push((key.as_ref(), value));
+        self
+    }
+
+    /// Compare keys without regard to ASCII case, like `PhfMap::new_ignore_ascii_case`.
+    pub fn ignore_ascii_case(&mut self, yes: bool) -> &mut Self {
+        self.ignore_case = yes;
+        self
+    }
+
+    /// Uses the given keysig instead of searching for one, like `PhfMap::with_keysig`.
+    pub fn keysig(&mut self, sig: &'a [isize]) -> &mut Self {
+        self.keysig = Some(sig);
+        self
+    }
+
+    /// Runs the search and returns the source code for the module.
+    pub fn generate(&self) -> Result<String, PhfBuildError> {
+        let keys: Vec<Key<'_>> = self.entries.iter().map(|&(key, _)| key).collect();
+        let layout = match self.keysig {
+            Some(sig) => Layout::with_keysig(&keys, sig, self.ignore_case)?,
+            None => Layout::new(&keys, self.ignore_case)?,
+        };
+
+        let mut out = String::new();
+        self.write_module(&mut out, &layout).expect("writing to a `String` cannot fail");
+        Ok(out)
+    }
+
+    fn write_module(&self, out: &mut String, layout: &Layout) -> std::fmt::Result {
+        let slot_type = if layout.has_wide_slots() { "u16" } else { "u8" };
+
+        writeln!(out, "// Generated by const-phf. Do not edit.")?;
+        writeln!(out)?;
+        writeln!(out, "#[allow(dead_code)]")?;
+        writeln!(out, "pub mod {} {{", self.name)?;
+
+        writeln!(
+            out,
+            "    pub static ENTRIES: [(&[u8], {}); {}] = [",
+            self.value_type,
+            self.entries.len(),
+        )?;
+        for &(key, value) in &self.entries {
+            writeln!(out, "        (b\"{}\", {}),", escape_key(key), value)?;
+        }
+        writeln!(out, "    ];")?;
+        writeln!(out)?;
+
+        let sig: Vec<String> = layout.sig.iter().map(|idx| idx.to_string()).collect();
+        writeln!(out, "    const SIG: [isize; {}] = [{}];", sig.len(), sig.join(", "))?;
+        writeln!(out, "    const MAX_HASH: usize = {};", layout.max_hash)?;
+        writeln!(out, "    const SENTINEL: {} = {}::MAX;", slot_type, slot_type)?;
+        writeln!(out)?;
+
+        writeln!(out, "    const ASSOC_VALUES: [u16; 256] = [")?;
+        write_rows(out, "        ", layout.assoc_values.iter().map(|v| v.to_string()))?;
+        writeln!(out, "    ];")?;
+        writeln!(out)?;
+
+        writeln!(out, "    const TABLE: [{}; {}] = [", slot_type, layout.table.len())?;
+        let slots = layout.table.iter().map(|slot| match slot {
+            Some(idx) => idx.to_string(),
+            None => "SENTINEL".to_owned(),
+        });
+        write_rows(out, "        ", slots)?;
+        writeln!(out, "    ];")?;
+        writeln!(out)?;
+
+        let (fold, key_eq) = if layout.ignore_case {
+            (".to_ascii_lowercase()", "ENTRIES[idx].0.eq_ignore_ascii_case(key)")
+        } else {
+            ("", "ENTRIES[idx].0 == key")
+        };
+
+        write!(out, "{}", HASH_FN.replace("{fold}", fold))?;
+        writeln!(out)?;
+        write!(out, "{}", GET_INDEX_FN.replace("{key_eq}", key_eq))?;
+        writeln!(out)?;
+        writeln!(out, "    pub fn get(key: &[u8]) -> Option<&'static {}> {{", self.value_type)?;
+        writeln!(out, "        get_index(key).map(|idx| &ENTRIES[idx].1)")?;
+        writeln!(out, "    }}")?;
+        writeln!(out, "}}")
+    }
+}
+
+const HASH_FN: &str = "    fn hash(key: &[u8]) -> usize {
+        let mut hash = key.len();
+        for &idx in SIG.iter() {
+            let abs_idx = idx.unsigned_abs();
+            if abs_idx < key.len() {
+                let c = if idx < 0 { key[key.len() - abs_idx] } else { key[abs_idx] };
+                hash += ASSOC_VALUES[c{fold} as usize] as usize;
+            }
+        }
+
+        hash
+    }
+";
+
+const GET_INDEX_FN: &str = "    pub fn get_index(key: &[u8]) -> Option<usize> {
+        let hash = hash(key);
+        if hash > MAX_HASH || TABLE[hash] == SENTINEL {
+            return None;
+        }
+
+        let idx = TABLE[hash] as usize;
+        if {key_eq} {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+";
+
+/// Writes `key` as the contents of a byte string literal.
+fn escape_key(key: Key<'_>) -> String {
+    key.iter()
+        .flat_map(|&c| std::ascii::escape_default(c))
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generate() {
+        let mut map = RustMap::new("keywords", "u32");
+        map.entry("fn", "1")
+            .entry("let", "2")
+            .entry(b"mut", "3")
+            .entry("while", "4");
+
+        let code = map.generate().unwrap();
+        let layout = Layout::new(&[b"fn", b"let", b"mut", b"while"], false).unwrap();
+
+        assert!(code.contains("pub mod keywords {"));
+        assert!(code.contains("pub static ENTRIES: [(&[u8], u32); 4] = ["));
+        assert!(code.contains("        (b\"while\", 4),"));
+        assert!(code.contains(&format!("const MAX_HASH: usize = {};", layout.max_hash)));
+        assert!(code.contains(&format!("const TABLE: [u8; {}] = [", layout.max_hash + 1)));
+        assert!(code.contains("pub fn get(key: &[u8]) -> Option<&'static u32> {"));
+    }
+
+    // The output of `keywords` and `sql`, checked in so that it can be compiled here. If either
+    // changes, `generated_code` fails until the file is overwritten with the new output.
+    include!("testdata/keywords.rs");
+    include!("testdata/sql.rs");
+
+    fn keywords() -> String {
+        let mut map = RustMap::new("keywords", "u32");
+        map.entry("fn", "1").entry("let", "2").entry(b"mut", "3").entry("while", "4");
+        map.generate().unwrap()
+    }
+
+    fn sql() -> String {
+        let mut map = RustMap::new("sql", "&str");
+        map.ignore_ascii_case(true)
+            .entry("select", "\"SELECT\"")
+            .entry("from", "\"FROM\"")
+            .entry("where", "\"WHERE\"")
+            .entry("join", "\"JOIN\"");
+        map.generate().unwrap()
+    }
+
+    #[test]
+    fn generated_code() {
+        assert_eq!(keywords(), include_str!("testdata/keywords.rs"));
+        assert_eq!(sql(), include_str!("testdata/sql.rs"));
+
+        assert_eq!(keywords::get(b"let"), Some(&2));
+        assert_eq!(keywords::get_index(b"while"), Some(3));
+        assert_eq!(keywords::get(b"loop"), None);
+        assert_eq!(keywords::get(b"LET"), None);
+        assert_eq!(keywords::get(b""), None);
+
+        assert_eq!(sql::get(b"SELECT"), Some(&"SELECT"));
+        assert_eq!(sql::get(b"Join"), Some(&"JOIN"));
+        assert_eq!(sql::get(b"joins"), None);
+    }
+}
@@ -0,0 +1,69 @@
+// Generated by const-phf. Do not edit.
+
+#[allow(dead_code)]
+pub mod keywords {
+    pub static ENTRIES: [(&[u8], u32); 4] = [
+        (b"fn", 1),
+        (b"let", 2),
+        (b"mut", 3),
+        (b"while", 4),
+    ];
+
+    const SIG: [isize; 1] = [0];
+    const MAX_HASH: usize = 6;
+    const SENTINEL: u8 = u8::MAX;
+
+    const ASSOC_VALUES: [u16; 256] = [
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 0, 6, 6, 6, 6, 6, 0, 3, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 0, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+        6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6, 6,
+    ];
+
+    const TABLE: [u8; 7] = [
+        SENTINEL, SENTINEL, 0, 1, SENTINEL, 3, 2,
+    ];
+
+    fn hash(key: &[u8]) -> usize {
+        let mut hash = key.len();
+        for &idx in SIG.iter() {
+            let abs_idx = idx.unsigned_abs();
+            if abs_idx < key.len() {
+                let c = if idx < 0 { key[key.len() - abs_idx] } else { key[abs_idx] };
+                hash += ASSOC_VALUES[c as usize] as usize;
+            }
+        }
+
+        hash
+    }
+
+    pub fn get_index(key: &[u8]) -> Option<usize> {
+        let hash = hash(key);
+        if hash > MAX_HASH || TABLE[hash] == SENTINEL {
+            return None;
+        }
+
+        let idx = TABLE[hash] as usize;
+        if ENTRIES[idx].0 == key {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(key: &[u8]) -> Option<&'static u32> {
+        get_index(key).map(|idx| &ENTRIES[idx].1)
+    }
+}
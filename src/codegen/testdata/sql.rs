@@ -0,0 +1,69 @@
+// Generated by const-phf. Do not edit.
+
+#[allow(dead_code)]
+pub mod sql {
+    pub static ENTRIES: [(&[u8], &str); 4] = [
+        (b"select", "SELECT"),
+        (b"from", "FROM"),
+        (b"where", "WHERE"),
+        (b"join", "JOIN"),
+    ];
+
+    const SIG: [isize; 1] = [0];
+    const MAX_HASH: usize = 7;
+    const SENTINEL: u8 = u8::MAX;
+
+    const ASSOC_VALUES: [u16; 256] = [
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 0, 7, 7, 7, 3, 7, 7, 7, 7, 7,
+        7, 7, 7, 0, 7, 7, 7, 0, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+        7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    ];
+
+    const TABLE: [u8; 8] = [
+        SENTINEL, SENTINEL, SENTINEL, SENTINEL, 1, 2, 0, 3,
+    ];
+
+    fn hash(key: &[u8]) -> usize {
+        let mut hash = key.len();
+        for &idx in SIG.iter() {
+            let abs_idx = idx.unsigned_abs();
+            if abs_idx < key.len() {
+                let c = if idx < 0 { key[key.len() - abs_idx] } else { key[abs_idx] };
+                hash += ASSOC_VALUES[c.to_ascii_lowercase() as usize] as usize;
+            }
+        }
+
+        hash
+    }
+
+    pub fn get_index(key: &[u8]) -> Option<usize> {
+        let hash = hash(key);
+        if hash > MAX_HASH || TABLE[hash] == SENTINEL {
+            return None;
+        }
+
+        let idx = TABLE[hash] as usize;
+        if ENTRIES[idx].0.eq_ignore_ascii_case(key) {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(key: &[u8]) -> Option<&'static &str> {
+        get_index(key).map(|idx| &ENTRIES[idx].1)
+    }
+}
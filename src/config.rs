@@ -0,0 +1,215 @@
+//! Parameters for the search that builds a perfect hash.
+
+/// The positions the keysig search tries first.
+const DEFAULT_POSITIONS: &[isize] = &[0, 1, 2, 3, -1, -2, -3];
+
+/// The amounts by which the random search raises an associated value, in turn. Best by test.
+const DEFAULT_INCREMENTS: &[u16] = &[1, 3, 4];
+
+const DEFAULT_MAX_TRIES: usize = 10_000;
+
+const DEFAULT_ATTEMPTS: usize = 4;
+
+/// How to search for the associated values of a map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Search {
+    /// `find_assoc_values_random`
+    Random,
+
+    /// `find_assoc_values_backtracking`
+    Backtracking,
+}
+
+/// How a `PhfMap` hashes its keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Sum an associated value for each character at a few positions in the key, the way gperf
+    /// does. Lookups are fast and the table is small, but the search slows down past a couple of
+    /// hundred keys.
+    Keysig,
+
+    /// Hash every byte of the key, then add a displacement chosen for the bucket that the key
+    /// falls in, as in CHD and PTHash. This scales to tens of thousands of keys, at the cost of
+    /// two bytes per bucket of four keys and a slower hash function.
+    Displacement,
+}
+
+/// Parameters for the search done by `PhfMap::with_config`.
+///
+/// Each method returns a modified copy, so a configuration can be built in a `const`:
+///
+/// ```
+/// use const_phf::{PhfConfig, PhfMap};
+///
+/// const CONFIG: PhfConfig<'static> = PhfConfig::new().increments(&[1, 2]).max_tries(50_000);
+/// const KVS: &[(&[u8], usize)] = &[(b"fn", 1), (b"let", 2), (b"mut", 3)];
+/// const MAP: PhfMap<'static, usize> = PhfMap::with_config(KVS, &CONFIG);
+///
+/// assert_eq!(MAP.get(b"let"), Some(&2));
+/// ```
+///
+/// `PhfConfig::new()` gives the same map as `PhfMap::new`.
+#[derive(Clone, Copy, Debug)]
+pub struct PhfConfig<'c> {
+    pub(crate) strategy: Strategy,
+    pub(crate) ignore_case: bool,
+    pub(crate) include_len: bool,
+    pub(crate) positions: &'c [isize],
+    pub(crate) greedy_keysig: bool,
+    pub(crate) search: Search,
+    pub(crate) increments: &'c [u16],
+    pub(crate) max_tries: usize,
+    pub(crate) seed: u64,
+    pub(crate) attempts: usize,
+    pub(crate) max_sparsity: usize,
+    pub(crate) min_load: usize,
+}
+
+impl<'c> PhfConfig<'c> {
+    pub const fn new() -> Self {
+        PhfConfig {
+            strategy: Strategy::Keysig,
+            ignore_case: false,
+            include_len: true,
+            positions: DEFAULT_POSITIONS,
+            greedy_keysig: false,
+            search: Search::Random,
+            increments: DEFAULT_INCREMENTS,
+            max_tries: DEFAULT_MAX_TRIES,
+            seed: 0,
+            attempts: DEFAULT_ATTEMPTS,
+            max_sparsity: crate::MAX_TABLE_SPARSITY,
+            min_load: 0,
+        }
+    }
+
+    /// Sets how keys are hashed. The default is `Strategy::Keysig`.
+    ///
+    /// `Strategy::Displacement` only uses `ignore_ascii_case`, `seed`, `attempts` and the load
+    /// factor. The rest of the parameters apply to `Strategy::Keysig`.
+    pub const fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Compare keys without regard to ASCII case, like `PhfMap::new_ignore_ascii_case`.
+    pub const fn ignore_ascii_case(mut self, yes: bool) -> Self {
+        self.ignore_case = yes;
+        self
+    }
+
+    /// Whether the length of a key is added to its hash, as it is by default.
+    ///
+    /// Without it, keys that differ only in length can't be told apart, so the keysig has to
+    /// separate them. The hash function is one addition shorter.
+    pub const fn include_len(mut self, yes: bool) -> Self {
+        self.include_len = yes;
+        self
+    }
+
+    /// Sets the positions that the keysig search tries first, in every combination.
+    ///
+    /// The default is the first four characters and the last three. If no combination of these
+    /// works, the search moves on to every position at which the keys differ.
+    pub const fn candidate_positions(mut self, positions: &'c [isize]) -> Self {
+        self.positions = positions;
+        self
+    }
+
+    /// Choose the keysig greedily, like `PhfMap::with_greedy_keysig`.
+    pub const fn greedy_keysig(mut self, yes: bool) -> Self {
+        self.greedy_keysig = yes;
+        self
+    }
+
+    /// Search for associated values by backtracking, like `PhfMap::with_backtracking`.
+    ///
+    /// The backtracking search ignores `increments`, `max_tries`, `seed` and `attempts`. It is
+    /// always used with `min_load_factor`.
+    pub const fn backtracking(mut self, yes: bool) -> Self {
+        self.search = if yes { Search::Backtracking } else { Search::Random };
+        self
+    }
+
+    /// Sets the amounts by which the random search raises an associated value after a collision.
+    ///
+    /// Each retry picks one of the amounts at random. The default is `[1, 3, 4]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `increments` is empty.
+    pub const fn increments(mut self, increments: &'c [u16]) -> Self {
+        assert!(!increments.is_empty(), "`increments` must not be empty");
+        self.increments = increments;
+        self
+    }
+
+    /// Sets how many collisions the random search gets past, by raising an associated value, before
+    /// it gives up on a seed. The default is 10,000.
+    pub const fn max_tries(mut self, max_tries: usize) -> Self {
+        self.max_tries = max_tries;
+        self
+    }
+
+    /// Sets the seed of the random search. The default is 0.
+    ///
+    /// If the search fails with one seed, it tries the next, up to `attempts` seeds in all.
+    /// `PhfMap::seed` returns the seed that worked, so passing it here with `attempts(1)` rebuilds
+    /// the same map without repeating the failed attempts.
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets how many seeds the random search tries before it gives up with `SearchExhausted` or
+    /// `TableOverflow`. The default is 4.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `attempts` is zero.
+    pub const fn attempts(mut self, attempts: usize) -> Self {
+        assert!(attempts > 0, "`attempts` must not be zero");
+        self.attempts = attempts;
+        self
+    }
+
+    /// Limits the table to `max_sparsity` slots per key.
+    ///
+    /// A search that would need a larger table fails with `TableOverflow`, or with `NoSolution`
+    /// when backtracking. The default is 8, or less if `TABLE` is smaller.
+    pub const fn max_sparsity(mut self, max_sparsity: usize) -> Self {
+        self.max_sparsity = max_sparsity;
+        self
+    }
+
+    /// Requires a minimal perfect hash, one that maps the keys to `0..n` with no empty slots.
+    ///
+    /// This is `min_load_factor(100)`.
+    pub const fn minimal(self) -> Self {
+        self.min_load_factor(100)
+    }
+
+    /// Requires at least `percent` of the slots in the table to hold a key.
+    ///
+    /// Hash values are counted from the length of the shortest key, since no key can hash lower
+    /// than that. The random search rarely finds such a dense table, so the associated values are
+    /// searched for by backtracking, as if `backtracking` were set.
+    ///
+    /// With `Strategy::Displacement`, this sets the load factor of the table, which is otherwise
+    /// 90 percent.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless `percent` is between 1 and 100.
+    pub const fn min_load_factor(mut self, percent: usize) -> Self {
+        assert!(percent > 0 && percent <= 100, "`percent` must be between 1 and 100");
+        self.min_load = percent;
+        self
+    }
+}
+
+impl Default for PhfConfig<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
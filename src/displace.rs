@@ -0,0 +1,206 @@
+//! The hash-and-displace strategy, for key sets too large for a keysig search.
+//!
+//! Every byte of each key is hashed. The upper half of the hash picks a bucket, and each bucket
+//! has a displacement (called a pilot in PTHash) that is mixed into the hash to pick a slot.
+//! Buckets are placed from largest to smallest, trying pilots in turn until every key in the bucket
+//! lands in an empty slot. The pilots are stored as `u16`s in the table, after the slots.
+
+use crate::config::{PhfConfig, Strategy};
+use crate::error::PhfBuildError;
+use crate::rng::{hash_bytes, mix};
+use crate::sig::Sig;
+use crate::util::{Entries, Key};
+use crate::{PhfMap, SENTINEL};
+
+/// The average number of keys in a bucket.
+const BUCKET_LEN: usize = 4;
+
+/// A bucket with more keys than this is so unlikely that the search starts over with a new seed.
+const MAX_BUCKET_LEN: usize = 32;
+
+/// The percentage of slots that hold a key unless `PhfConfig::min_load_factor` says otherwise.
+const DEFAULT_LOAD: usize = 90;
+
+impl<'a, T, const TABLE: usize, const SIG: usize> PhfMap<'a, T, TABLE, SIG> {
+    pub(crate) const fn build_displaced(
+        kvs: Entries<'a, T>,
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        let load = if config.min_load > 0 { config.min_load } else { DEFAULT_LOAD };
+        let slots = kvs.len() * 100 / load;
+
+        let mut map = PhfMap {
+            keys: kvs,
+            sig: Sig::new(0),
+            ignore_case: config.ignore_case,
+            include_len: false,
+            seed: config.seed,
+            hash_offset: 0,
+            strategy: Strategy::Displacement,
+            buckets: if kvs.is_empty() { 1 } else { kvs.len().div_ceil(BUCKET_LEN) },
+            first_char: 0,
+            num_chars: 0,
+            assoc_width: 1,
+            table: [SENTINEL; TABLE],
+            max_hash: if slots > 0 { slots - 1 } else { 0 },
+        };
+
+        if map.table_len() > TABLE {
+            return Err(PhfBuildError::TableOverflow);
+        }
+
+        let mut attempt = 0;
+        while attempt < config.attempts {
+            map.seed = config.seed.wrapping_add(attempt as u64);
+            if map.place_buckets() {
+                return Ok(map);
+            }
+
+            map.table = [SENTINEL; TABLE];
+            attempt += 1;
+        }
+
+        Err(PhfBuildError::SearchExhausted)
+    }
+
+    /// Chooses a pilot for each bucket under the current seed. Returns `false` if some bucket
+    /// can't be placed with any pilot.
+    ///
+    /// The table holds at least a byte for each key and two for each bucket, so scratch space the
+    /// size of the table is enough to sort the keys by bucket.
+    const fn place_buckets(&mut self) -> bool {
+        let buckets = self.buckets;
+
+        // Count the keys in each bucket, then replace each count with the start of the bucket.
+        let mut end = [0u16; TABLE];
+        iter_keys!(key in self.keys => {
+            end[self.bucket(key)] += 1;
+        });
+
+        let mut start = 0;
+        let mut b = 0;
+        while b < buckets {
+            let len = end[b];
+            if len as usize > MAX_BUCKET_LEN {
+                return false;
+            }
+
+            end[b] = start;
+            start += len;
+            b += 1;
+        }
+
+        // Sort the keys by bucket, moving each entry of `end` from the start of its bucket to the
+        // end. The keys in bucket `b` end up between `end[b - 1]` and `end[b]`.
+        let mut members = [0u16; TABLE];
+        iter_keys!((i, key) in self.keys => {
+            let b = self.bucket(key);
+            members[end[b] as usize] = i as u16;
+            end[b] += 1;
+        });
+
+        // Large buckets are placed first, while there is still plenty of room.
+        let mut len = MAX_BUCKET_LEN;
+        while len > 0 {
+            let mut b = 0;
+            while b < buckets {
+                let start = if b == 0 { 0 } else { end[b - 1] as usize };
+                if end[b] as usize - start == len {
+                    let members = members.split_at(end[b] as usize).0.split_at(start).1;
+                    if !self.place_bucket(b, members) {
+                        return false;
+                    }
+                }
+
+                b += 1;
+            }
+
+            len -= 1;
+        }
+
+        true
+    }
+
+    /// Finds the first pilot that places each of `members` in an empty slot, and stores it as the
+    /// pilot of bucket `b`.
+    const fn place_bucket(&mut self, b: usize, members: &[u16]) -> bool {
+        let mut hashes = [0u64; MAX_BUCKET_LEN];
+        iter!((i, k) in members => {
+            hashes[i] = hash_bytes(self.keys.key(k as usize), self.ignore_case, self.seed);
+        });
+
+        let hashes = hashes.split_at(members.len()).0;
+        let mut pilot = 0;
+        loop {
+            if self.try_pilot(members, hashes, pilot) {
+                self.set_pilot(b, pilot);
+                return true;
+            }
+
+            if pilot == u16::MAX {
+                return false;
+            }
+
+            pilot += 1;
+        }
+    }
+
+    /// Places each of `members` in the slot that `pilot` picks for it, unless one of those slots is
+    /// taken, in which case the table is left as it was.
+    const fn try_pilot(&mut self, members: &[u16], hashes: &[u64], pilot: u16) -> bool {
+        let pilot_hash = mix(pilot as u64);
+        let width = self.slot_width();
+        let empty = (1 << (8 * width)) - 1;
+        iter!((i, k) in members => {
+            let slot = self.displace_mixed(hashes[i], pilot_hash);
+            if self.read_le(slot * width, width) != empty {
+                let mut j = 0;
+                while j < i {
+                    let slot = self.displace_mixed(hashes[j], pilot_hash);
+                    self.set_slot(slot, None);
+                    j += 1;
+                }
+
+                return false;
+            }
+
+            self.set_slot(slot, Some(k as usize));
+        });
+
+        true
+    }
+
+    pub(crate) const fn displaced_hash(&self, key: Key<'_>) -> usize {
+        let hash = hash_bytes(key, self.ignore_case, self.seed);
+        self.displace(hash, self.pilot(self.bucket_of_hash(hash)))
+    }
+
+    /// Picks a slot for a key with the given hash. Mixing the hash again, rather than just
+    /// combining it with the pilot, keeps the keys of a bucket from moving in lockstep.
+    const fn displace(&self, hash: u64, pilot: u16) -> usize {
+        self.displace_mixed(hash, mix(pilot as u64))
+    }
+
+    /// Like `displace`, with the pilot already mixed, so that the search mixes each pilot it tries
+    /// once rather than once for every key in the bucket.
+    const fn displace_mixed(&self, hash: u64, pilot_hash: u64) -> usize {
+        (mix(hash ^ pilot_hash) % (self.max_hash as u64 + 1)) as usize
+    }
+
+    const fn bucket(&self, key: Key<'_>) -> usize {
+        self.bucket_of_hash(hash_bytes(key, self.ignore_case, self.seed))
+    }
+
+    const fn bucket_of_hash(&self, hash: u64) -> usize {
+        (((hash >> 32) * self.buckets as u64) >> 32) as usize
+    }
+
+    /// The pilots come after the slots in the table, where a keysig map has its associated values.
+    const fn pilot(&self, bucket: usize) -> u16 {
+        self.read_le(self.assoc_start() + 2 * bucket, 2) as u16
+    }
+
+    const fn set_pilot(&mut self, bucket: usize, pilot: u16) {
+        self.write_le(self.assoc_start() + 2 * bucket, 2, pilot as usize);
+    }
+}
@@ -0,0 +1,224 @@
+//! Errors reported when a perfect hash cannot be constructed.
+
+use std::fmt;
+
+use crate::arr::ConstArray;
+use crate::sig::Sig;
+use crate::util::{Entries, Key};
+
+/// The reason a `PhfMap` could not be built.
+///
+/// Indices refer to positions in the slice of key-value pairs passed to the constructor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhfBuildError {
+    /// More than `MAX_KEYS` keys were given.
+    TooManyKeys,
+
+    /// The same key appears more than once in the input.
+    DuplicateKeys { first: usize, second: usize },
+
+    /// Two keys have the same length and the same characters at every position in `sig`.
+    ///
+    /// When the keysig was chosen automatically, `sig` holds the positions that were tried first.
+    /// Positions further into the keys were tried as well, but none of them could separate the
+    /// pair either. Only the first `MAX_KEYSIG_LEN` positions of a longer keysig are kept, in
+    /// which case `truncated` is set.
+    NoUniqueKeysig { first: usize, second: usize, sig: Keysig, truncated: bool },
+
+    /// The associated value search gave up after `max_tries` retries with each seed it tried, or
+    /// after `MAX_BACKTRACKING_STEPS` steps when backtracking.
+    SearchExhausted,
+
+    /// A hash value did not fit in the table.
+    TableOverflow,
+
+    /// The backtracking search tried every assignment of associated values, and none of them fit
+    /// the keys in the table, or in the number of slots that `PhfConfig::max_sparsity` allows.
+    NoSolution,
+
+    /// A keysig had more positions than the `SIG` parameter of the map allows.
+    KeysigTooLong,
+
+    /// A list of key positions was malformed. `offset` is the byte at which parsing failed.
+    InvalidKeyPositions { offset: usize },
+}
+
+impl PhfBuildError {
+    /// Aborts const evaluation with a message describing this error.
+    ///
+    /// `kvs` should be the input that caused the error, so the offending keys can be printed.
+    pub(crate) const fn panic<T>(&self, kvs: &[(Key<'_>, T)]) -> ! {
+        self.panic_with(Entries::Bytes(kvs))
+    }
+
+    /// Like `panic`, for input that may not be byte string pairs.
+    pub(crate) const fn panic_with<T>(&self, kvs: Entries<'_, T>) -> ! {
+        let msg = self.message(kvs);
+        panic!("{}", msg.as_str())
+    }
+
+    const fn message<T>(&self, kvs: Entries<'_, T>) -> Message {
+        let mut msg = Message::new();
+
+        match *self {
+            PhfBuildError::TooManyKeys => {
+                msg.push_str("too many keys (the maximum is ");
+                msg.push_usize(crate::MAX_KEYS);
+                msg.push_str(")");
+            }
+
+            PhfBuildError::DuplicateKeys { first, second } => {
+                msg.push_str("duplicate keys at indices ");
+                msg.push_key_pair(kvs, first, second);
+            }
+
+            PhfBuildError::NoUniqueKeysig { first, second, ref sig, truncated } => {
+                msg.push_str("keys at indices ");
+                msg.push_key_pair(kvs, first, second);
+                msg.push_str(" are indistinguishable by keysig [");
+                iter!((i, idx) in sig.as_slice() => {
+                    if i != 0 {
+                        msg.push_str(", ");
+                    }
+
+                    msg.push_isize(idx);
+                });
+
+                if truncated {
+                    msg.push_str(", ...");
+                }
+
+                msg.push_str("]");
+            }
+
+            PhfBuildError::SearchExhausted => msg.push_str("failed to find perfect hash"),
+            PhfBuildError::TableOverflow => msg.push_str("hash value does not fit in table"),
+            PhfBuildError::NoSolution => msg.push_str("no perfect hash fits in table"),
+
+            PhfBuildError::KeysigTooLong => msg.push_str("too many keysig positions"),
+
+            PhfBuildError::InvalidKeyPositions { offset } => {
+                msg.push_str("invalid key positions at offset ");
+                msg.push_usize(offset);
+            }
+        }
+
+        msg
+    }
+}
+
+/// The positions of a keysig, as reported by `PhfBuildError::NoUniqueKeysig`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Keysig(pub(crate) Sig);
+
+impl Keysig {
+    /// Returns the positions, counted from the start of the key if non-negative and from the end
+    /// if negative, so that `-1` is the last character.
+    pub const fn as_slice(&self) -> &[isize] {
+        self.0.as_slice()
+    }
+}
+
+impl fmt::Debug for Keysig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
+
+impl fmt::Display for PhfBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let no_keys: Entries<'_, ()> = Entries::Bytes(&[]);
+        f.write_str(self.message(no_keys).as_str())
+    }
+}
+
+impl std::error::Error for PhfBuildError {}
+
+const MAX_MESSAGE_LEN: usize = 256;
+
+/// A fixed-size buffer for formatting error messages in a `const fn`.
+///
+/// Anything past `MAX_MESSAGE_LEN` bytes is silently dropped.
+struct Message(ConstArray<u8, MAX_MESSAGE_LEN>);
+
+impl Message {
+    const fn new() -> Self {
+        Message(ConstArray::new(0))
+    }
+
+    const fn as_str(&self) -> &str {
+        // Only ASCII is ever pushed.
+        unsafe { std::str::from_utf8_unchecked(self.0.as_slice()) }
+    }
+
+    const fn push_byte(&mut self, c: u8) {
+        if self.0.len() < MAX_MESSAGE_LEN {
+            self.0.push(c);
+        }
+    }
+
+    const fn push_str(&mut self, s: &str) {
+        iter!(c in s.as_bytes() => {
+            self.push_byte(c);
+        });
+    }
+
+    const fn push_usize(&mut self, mut n: usize) {
+        let mut digits = [0u8; 20];
+        let mut len = 0;
+        loop {
+            digits[len] = b'0' + (n % 10) as u8;
+            len += 1;
+            n /= 10;
+
+            if n == 0 {
+                break;
+            }
+        }
+
+        while len > 0 {
+            len -= 1;
+            self.push_byte(digits[len]);
+        }
+    }
+
+    const fn push_isize(&mut self, n: isize) {
+        if n < 0 {
+            self.push_byte(b'-');
+        }
+
+        self.push_usize(n.unsigned_abs());
+    }
+
+    /// Pushes a key surrounded by backticks, escaping anything that isn't printable ASCII.
+    const fn push_key(&mut self, key: Key<'_>) {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+
+        self.push_byte(b'`');
+        iter!(c in key => {
+            if c.is_ascii_graphic() || c == b' ' {
+                self.push_byte(c);
+            } else {
+                self.push_str("\\x");
+                self.push_byte(HEX[(c >> 4) as usize]);
+                self.push_byte(HEX[(c & 0xf) as usize]);
+            }
+        });
+        self.push_byte(b'`');
+    }
+
+    /// Pushes "`first` and `second`", followed by the keys themselves if `kvs` contains them.
+    const fn push_key_pair<T>(&mut self, kvs: Entries<'_, T>, first: usize, second: usize) {
+        self.push_usize(first);
+        self.push_str(" and ");
+        self.push_usize(second);
+
+        if first < kvs.len() && second < kvs.len() {
+            self.push_str(" (");
+            self.push_key(kvs.key(first));
+            self.push_str(", ");
+            self.push_key(kvs.key(second));
+            self.push_str(")");
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Iterators over the entries of a `PhfMap`.
+
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+use crate::PhfMap;
+use crate::util::{Entries, Key};
+
+/// Iterates over the entries of a `PhfMap` in the order they were given.
+pub struct Iter<'a, T> {
+    kvs: Entries<'a, T>,
+    range: Range<usize>,
+}
+
+impl<'a, T> Iter<'a, T> {
+    pub(crate) fn new(kvs: Entries<'a, T>) -> Self {
+        Iter { kvs, range: 0..kvs.len() }
+    }
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (Key<'a>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.range.next().map(|i| (self.kvs.key(i), self.kvs.value(i)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.range.next_back().map(|i| (self.kvs.key(i), self.kvs.value(i)))
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+impl<T> FusedIterator for Iter<'_, T> {}
+
+/// Iterates over the entries of a `PhfMap` in the order they appear in its hash table.
+pub struct HashOrderIter<'m, 'a, T, const TABLE: usize, const SIG: usize> {
+    map: &'m PhfMap<'a, T, TABLE, SIG>,
+    hash: usize,
+}
+
+impl<'m, 'a, T, const TABLE: usize, const SIG: usize> HashOrderIter<'m, 'a, T, TABLE, SIG> {
+    pub(crate) fn new(map: &'m PhfMap<'a, T, TABLE, SIG>) -> Self {
+        HashOrderIter { map, hash: 0 }
+    }
+}
+
+impl<'a, T, const TABLE: usize, const SIG: usize> Iterator
+    for HashOrderIter<'_, 'a, T, TABLE, SIG>
+{
+    type Item = (Key<'a>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.hash <= self.map.max_hash {
+            let hash = self.hash;
+            self.hash += 1;
+
+            if let Some(idx) = self.map.slot(hash) {
+                let keys = self.map.keys;
+                return Some((keys.key(idx), keys.value(idx)));
+            }
+        }
+
+        None
+    }
+}
+
+impl<T, const TABLE: usize, const SIG: usize> FusedIterator
+    for HashOrderIter<'_, '_, T, TABLE, SIG>
+{
+}
@@ -1,305 +1,1562 @@
-#![feature(const_fn)]
-#![feature(const_if_match)]
-#![feature(const_loop)]
-#![feature(const_mut_refs)]
-#![feature(const_panic)]
-// Used only for `ConstArray`.
-#![feature(const_generics)]
-#![feature(const_transmute)]
-#![feature(const_raw_ptr_deref)]
-#![feature(slice_from_raw_parts)]
-#![feature(const_slice_from_raw_parts)]
-#![allow(incomplete_features)]
-
 #[macro_use] mod util;
+#[macro_use] mod macros;
 
 mod arr;
-mod set;
+pub mod codegen;
+mod config;
+mod displace;
+mod error;
+mod iter;
+mod ordered;
+mod phf_set;
+mod rng;
 mod sig;
 
-use arr::ConstArray;
-use set::{rarest_char_in_disjoint_union};
-use sig::{Sig, find_unique_keysig, keysig, is_keysig_unique};
-use util::{Key, key_eq};
-
-const MAX_KEYS: usize = 255;
-const SENTINEL: u8 = MAX_KEYS as u8;
-
+pub use config::{PhfConfig, Strategy};
+pub use error::{Keysig, PhfBuildError};
+pub use iter::{HashOrderIter, Iter};
+pub use ordered::{MAX_ORDERED_TABLE_LEN, OrderedPhfMap, ordered_table_len};
+pub use phf_set::{
+    PhfSet, set_table_len, set_table_len_with_key_positions, set_table_len_with_keysig,
+    set_table_len_with_keysig_len,
+};
+
+use config::Search;
+use rng::Rng;
+use sig::{
+    MAX_KEYSIG_LEN, Sig, find_duplicate_keys, find_keysig_collision, find_keysig_greedy,
+    find_unique_keysig, no_unique_keysig, parse_key_positions,
+};
+use util::{Entries, Key, key_eq};
+
+/// The largest number of keys whose indices fit in a single-byte table slot.
+const MAX_SMALL_KEYS: usize = u8::MAX as usize;
+
+/// Maps with more than `MAX_SMALL_KEYS` keys use two-byte table slots.
+///
+/// Wider slots would be pointless, since hash values (and thus the number of slots) are limited
+/// to `u16`.
+const MAX_KEYS: usize = u16::MAX as usize;
+
+/// Every byte of an empty table slot, regardless of its width.
+const SENTINEL: u8 = u8::MAX;
+
+/// The most slots per key a table may have, unless `PhfConfig::max_sparsity` says otherwise.
 const MAX_TABLE_SPARSITY: usize = 8;
-const MAX_TABLE_LEN: usize = MAX_KEYS * MAX_TABLE_SPARSITY;
 
-pub struct PhfMap<'a, T> {
-    keys: &'a [(Key<'a>, T)],
-    sig: Sig,
-
-    table: [u8; MAX_TABLE_LEN],
-    assoc_values: [u16; 256],
+/// The default size of the table in bytes.
+///
+/// This is enough for most maps with single-byte slots, but every map embeds its whole table, so
+/// a map with the default `TABLE` takes up over 2KB even if it only needs a few dozen bytes.
+pub const MAX_TABLE_LEN: usize = MAX_SMALL_KEYS * MAX_TABLE_SPARSITY;
+
+/// A table large enough for any hash function, used to search for one before the final table
+/// size is known.
+const SEARCH_TABLE_LEN: usize = (u16::MAX as usize + 1) * 2;
+
+/// A perfect hash map from byte strings to values of type `T`.
+///
+/// `TABLE` is the size of the hash table in bytes. Each slot holds the index of a key in a `u8`,
+/// or in a `u16` if there are more than 255 keys. The associated values of the characters used in
+/// keysigs come after the slots, in a byte each if they fit. The default is large enough for most
+/// maps with 255 keys or fewer, but is paid for in full by every map; larger maps must choose a
+/// bigger `TABLE`.
+///
+/// `SIG` is the most positions the keysig may have. Key sets with long shared prefixes and
+/// suffixes may need more than the default.
+///
+/// By default, keys are hashed the way gperf does. For thousands of keys, build the map with
+/// `with_config` and `Strategy::Displacement` instead.
+///
+/// Besides the map itself, the search needs scratch space: up to six bytes per byte of `TABLE`
+/// with `Strategy::Keysig`, four with `Strategy::Displacement`, or about 25 per vertex for an
+/// `OrderedPhfMap`, and another 16KB to find keys that can't be told apart. This is on the stack
+/// unless the map is built in a `const`, so a large map built at runtime may need a thread with a
+/// larger stack.
+///
+/// To avoid wasting space, use `table_len` to pick the smallest `TABLE` that fits:
+///
+/// ```
+/// use const_phf::PhfMap;
+///
+/// const KVS: &[(&[u8], usize)] = &[(b"fn", 1), (b"let", 2), (b"mut", 3)];
+/// const MAP: PhfMap<'static, usize, { const_phf::table_len(KVS) }> = PhfMap::new(KVS);
+/// ```
+pub struct PhfMap<
+    'a,
+    T,
+    const TABLE: usize = MAX_TABLE_LEN,
+    const SIG: usize = MAX_KEYSIG_LEN,
+> {
+    keys: Entries<'a, T>,
+    sig: Sig<SIG>,
+    ignore_case: bool,
+    include_len: bool,
+    seed: u64,
+
+    /// Subtracted from every hash value, so that the table of a minimal map starts at 0.
+    hash_offset: usize,
+
+    strategy: Strategy,
+
+    /// The number of pilots stored after the slots, for `Strategy::Displacement`.
+    buckets: usize,
+
+    /// The first character with an associated value in the table, and the number of characters
+    /// from there on that have one.
+    first_char: u8,
+    num_chars: usize,
+
+    /// The number of bytes used by each associated value in the table.
+    assoc_width: usize,
+
+    table: [u8; TABLE],
     max_hash: usize,
 }
 
-impl<'a, T> PhfMap<'a, T> {
+impl<'a, T, const TABLE: usize, const SIG: usize> PhfMap<'a, T, TABLE, SIG> {
     pub const fn new(kvs: &'a [(Key<'a>, T)]) -> Self {
         Self::with_keysig_len(kvs, 0)
     }
 
     pub const fn with_keysig_len(kvs: &'a [(Key<'a>, T)], len: usize) -> Self {
-        let mut keys: ConstArray<Key<'a>, MAX_KEYS> = ConstArray::new(b"");
-        iter!(ref kv in kvs => {
-            keys.push(kv.0);
-        });
+        match Self::try_with_keysig_len(kvs, len) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
 
-        let sig = expect_ok!(find_unique_keysig(keys.as_slice(), len));
+    pub const fn with_keysig(kvs: &'a [(Key<'a>, T)], sig: &[isize]) -> Self {
+        match Self::try_with_keysig(kvs, sig) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
 
-        Self::_with_keysig(kvs, sig)
+    /// Like `with_keysig`, but the keysig is written like the argument to gperf's `-k` option.
+    ///
+    /// Positions count from 1, `$` is the last character, `a-b` is an inclusive range and `*` is
+    /// every position. For example, `"1,2,5-8,$"` is the same as `&[0, 1, 4, 5, 6, 7, -1]`.
+    pub const fn with_key_positions(kvs: &'a [(Key<'a>, T)], spec: &str) -> Self {
+        match Self::try_with_key_positions(kvs, spec) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
     }
 
-    pub const fn with_keysig(kvs: &'a [(Key<'a>, T)], sig: &[isize]) -> Self {
+    /// Like `new`, but chooses the keysig greedily, the way gperf does.
+    ///
+    /// `new` tries combinations of positions until it finds the smallest keysig that works, which
+    /// can take too long when no short keysig does. This adds one position at a time instead,
+    /// though the keysig it finds may have more positions than necessary, which slows down the
+    /// search for associated values. That scales to hundreds of keys at runtime, but const
+    /// evaluation may run out of time past a hundred or two. `codegen` can build larger maps in a
+    /// build script.
+    pub const fn with_greedy_keysig(kvs: &'a [(Key<'a>, T)]) -> Self {
+        match Self::try_with_greedy_keysig(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    /// Like `new`, but searches for associated values by backtracking.
+    ///
+    /// `new` raises associated values until the keys stop colliding, and gives up after a fixed
+    /// number of tries even if a solution exists. This tries every assignment of associated values
+    /// in turn instead. If it fails with `NoSolution`, no associated values fit the keys under the
+    /// chosen keysig in a table of `TABLE` bytes with at most `PhfConfig::max_sparsity` slots per
+    /// key, which is 8 by default. It usually finds a smaller table than `new`, but takes much
+    /// longer once there are more than a few dozen keys.
+    pub const fn with_backtracking(kvs: &'a [(Key<'a>, T)]) -> Self {
+        match Self::try_with_backtracking(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    /// Like `new`, but builds a minimal perfect hash, under which the keys hash to `0..n`.
+    ///
+    /// The table has no empty slots, so `TABLE` can be as small as one slot per key. This searches
+    /// by backtracking, like `with_backtracking`, and fails with `NoSolution` if no minimal perfect
+    /// hash exists under the chosen keysig. The length of a key is left out of its hash, which
+    /// would otherwise spread the hash values too far to fit.
+    pub const fn new_minimal(kvs: &'a [(Key<'a>, T)]) -> Self {
+        match Self::try_new_minimal(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    pub const fn try_new(kvs: &'a [(Key<'a>, T)]) -> Result<Self, PhfBuildError> {
+        Self::try_with_keysig_len(kvs, 0)
+    }
+
+    pub const fn try_new_minimal(kvs: &'a [(Key<'a>, T)]) -> Result<Self, PhfBuildError> {
+        let config = PhfConfig::new().minimal().include_len(false);
+        Self::build(Entries::Bytes(kvs), 0, &config)
+    }
+
+    /// Like `new`, but keys are compared without regard to ASCII case.
+    ///
+    /// Lookups fold ASCII case while hashing, so `get(b"SELECT")` and `get(b"select")` find the
+    /// same entry without needing to lowercase the key first.
+    pub const fn new_ignore_ascii_case(kvs: &'a [(Key<'a>, T)]) -> Self {
+        match Self::try_new_ignore_ascii_case(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    /// Like `try_new`, but keys are compared without regard to ASCII case.
+    pub const fn try_new_ignore_ascii_case(
+        kvs: &'a [(Key<'a>, T)],
+    ) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Bytes(kvs), 0, &PhfConfig::new().ignore_ascii_case(true))
+    }
+
+    /// Like `new`, but with string keys.
+    pub const fn new_str(kvs: &'a [(&'a str, T)]) -> Self {
+        match Self::try_new_str(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic_with(Entries::Str(kvs)),
+        }
+    }
+
+    /// Like `try_new`, but with string keys.
+    pub const fn try_new_str(kvs: &'a [(&'a str, T)]) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Str(kvs), 0, &PhfConfig::new())
+    }
+
+    pub const fn try_with_keysig_len(
+        kvs: &'a [(Key<'a>, T)],
+        len: usize,
+    ) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Bytes(kvs), len, &PhfConfig::new())
+    }
+
+    pub const fn try_with_keysig(
+        kvs: &'a [(Key<'a>, T)],
+        sig: &[isize],
+    ) -> Result<Self, PhfBuildError> {
+        Self::build_with_keysig(Entries::Bytes(kvs), sig, &PhfConfig::new())
+    }
+
+    pub const fn try_with_key_positions(
+        kvs: &'a [(Key<'a>, T)],
+        spec: &str,
+    ) -> Result<Self, PhfBuildError> {
+        Self::build_with_key_positions(Entries::Bytes(kvs), spec, &PhfConfig::new())
+    }
+
+    pub const fn try_with_greedy_keysig(kvs: &'a [(Key<'a>, T)]) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Bytes(kvs), 0, &PhfConfig::new().greedy_keysig(true))
+    }
+
+    pub const fn try_with_backtracking(kvs: &'a [(Key<'a>, T)]) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Bytes(kvs), 0, &PhfConfig::new().backtracking(true))
+    }
+
+    /// Like `new`, but with the search parameters in `config`.
+    pub const fn with_config(kvs: &'a [(Key<'a>, T)], config: &PhfConfig<'_>) -> Self {
+        match Self::try_with_config(kvs, config) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    pub const fn try_with_config(
+        kvs: &'a [(Key<'a>, T)],
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Bytes(kvs), 0, config)
+    }
+
+    /// Like `with_keysig`, but with the search parameters in `config`.
+    ///
+    /// Since the keysig is given, the parameters of the keysig search, `candidate_positions` and
+    /// `greedy_keysig`, have no effect, and neither does `strategy`.
+    pub const fn with_keysig_and_config(
+        kvs: &'a [(Key<'a>, T)],
+        sig: &[isize],
+        config: &PhfConfig<'_>,
+    ) -> Self {
+        match Self::try_with_keysig_and_config(kvs, sig, config) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    pub const fn try_with_keysig_and_config(
+        kvs: &'a [(Key<'a>, T)],
+        sig: &[isize],
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        Self::build_with_keysig(Entries::Bytes(kvs), sig, config)
+    }
+
+    /// Like `with_key_positions`, but with the search parameters in `config`, as for
+    /// `with_keysig_and_config`.
+    pub const fn with_key_positions_and_config(
+        kvs: &'a [(Key<'a>, T)],
+        spec: &str,
+        config: &PhfConfig<'_>,
+    ) -> Self {
+        match Self::try_with_key_positions_and_config(kvs, spec, config) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    pub const fn try_with_key_positions_and_config(
+        kvs: &'a [(Key<'a>, T)],
+        spec: &str,
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        Self::build_with_key_positions(Entries::Bytes(kvs), spec, config)
+    }
+
+    const fn build(
+        kvs: Entries<'a, T>,
+        len: usize,
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        if let Err(e) = check_keys(kvs, config.ignore_case) {
+            return Err(e);
+        }
+
+        if let Strategy::Displacement = config.strategy {
+            return Self::build_displaced(kvs, config);
+        }
+
+        let sig = if config.greedy_keysig {
+            find_keysig_greedy(kvs, config)
+        } else {
+            find_unique_keysig(kvs, len, config)
+        };
+
+        match sig {
+            Ok(sig) => Self::_with_keysig(kvs, sig, config),
+            Err(e) => Err(e),
+        }
+    }
+
+    const fn build_for(
+        kvs: Entries<'a, T>,
+        keysigs: Keysigs<'_>,
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        match keysigs {
+            Keysigs::Search(len) => Self::build(kvs, len, config),
+            Keysigs::Given(sig) => Self::build_with_keysig(kvs, sig, config),
+            Keysigs::Positions(spec) => Self::build_with_key_positions(kvs, spec, config),
+        }
+    }
+
+    const fn build_with_keysig(
+        kvs: Entries<'a, T>,
+        sig: &[isize],
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        if sig.len() > SIG {
+            return Err(PhfBuildError::KeysigTooLong);
+        }
+
+        if let Err(e) = check_keys(kvs, config.ignore_case) {
+            return Err(e);
+        }
+
         let mut keysig = Sig::new(0);
         iter!(idx in sig => {
             keysig.push(idx);
         });
 
-        Self::_with_keysig(kvs, keysig)
+        Self::_with_keysig(kvs, keysig, config)
     }
 
-    const fn _with_keysig(kvs: &'a [(Key<'a>, T)], sig: Sig) -> Self {
-        assert!(kvs.len() <= MAX_KEYS);
-
-        let mut keys: ConstArray<Key<'a>, MAX_KEYS> = ConstArray::new(b"");
-        iter!(ref kv in kvs => {
-            keys.push(kv.0);
+    const fn build_with_key_positions(
+        kvs: Entries<'a, T>,
+        spec: &str,
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        let mut max_key_len = 0;
+        iter_keys!(key in kvs => {
+            if key.len() > max_key_len {
+                max_key_len = key.len();
+            }
         });
-        let keys = keys.as_slice();
 
+        let sig = match parse_key_positions(spec.as_bytes(), max_key_len) {
+            Ok(sig) => sig,
+            Err(e) => return Err(e),
+        };
+
+        match check_keys(kvs, config.ignore_case) {
+            Ok(()) => Self::_with_keysig(kvs, sig, config),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Builds a map with the given keysig, once `check_keys` has passed.
+    const fn _with_keysig(
+        kvs: Entries<'a, T>,
+        sig: Sig<SIG>,
+        config: &PhfConfig<'_>,
+    ) -> Result<Self, PhfBuildError> {
+        let ignore_case = config.ignore_case;
         let mut map = PhfMap {
             keys: kvs,
             sig,
-            table: [SENTINEL; MAX_TABLE_LEN],
-            assoc_values: [0; 256],
+            ignore_case,
+            include_len: config.include_len,
+            seed: config.seed,
+            hash_offset: 0,
+            strategy: Strategy::Keysig,
+            buckets: 0,
+            first_char: 0,
+            num_chars: 0,
+            assoc_width: 1,
+            table: [SENTINEL; TABLE],
             max_hash: 0,
         };
 
-        assert!(is_keysig_unique(keys, sig.as_slice()));
+        // No key can hash lower than the shortest one does when every associated value is zero.
+        if config.min_load > 0 && config.include_len {
+            map.hash_offset = usize::MAX;
+            iter_keys!(key in kvs => {
+                if key.len() < map.hash_offset {
+                    map.hash_offset = key.len();
+                }
+            });
+
+            if kvs.is_empty() {
+                map.hash_offset = 0;
+            }
+        }
+
+        if let Some((first, second)) =
+            find_keysig_collision::<T, SIG>(kvs, sig.as_slice(), config)
+        {
+            return Err(no_unique_keysig(first, second, sig.as_slice()));
+        }
+
+        let char_freq = count_char_frequency(kvs, sig.as_slice(), ignore_case);
+        map.set_char_range(&char_freq);
+
+        // Each key needs a slot of its own, which also bounds the scratch space by `TABLE`.
+        if kvs.len() > TABLE {
+            return Err(PhfBuildError::TableOverflow);
+        }
+
+        let mut order = [0; TABLE];
+        let order = order.split_at_mut(kvs.len()).0;
+        order_keywords::<T, TABLE>(order, kvs, sig.as_slice(), ignore_case, &char_freq);
+
+        // The random search almost never finds a table dense enough for `min_load`, and would use
+        // up every try on every seed before giving up.
+        let search = if config.min_load > 0 { Search::Backtracking } else { config.search };
 
-        let char_freq = count_char_frequency(keys, sig.as_slice());
-        expect_ok!(find_assoc_values_random(&mut map, &char_freq));
+        let mut assoc_values = [0; 256];
+        let res = match search {
+            Search::Random => {
+                find_assoc_values_random(&mut map, &mut assoc_values, order, &char_freq, config)
+            }
+
+            Search::Backtracking => {
+                find_assoc_values_backtracking(&mut map, &mut assoc_values, order, config)
+            }
+        };
+
+        if let Err(e) = res {
+            return Err(e);
+        }
 
-        iter!(key in keys => {
-            let hash = map.hash(key);
+        iter_keys!(key in kvs => {
+            let hash = map.search_hash(key, &assoc_values);
             if hash > map.max_hash {
                 map.max_hash = hash;
             }
         });
 
-        assert!(map.max_hash <= u16::max_value() as usize);
+        if map.max_hash > u16::MAX as usize {
+            return Err(PhfBuildError::TableOverflow);
+        }
+
+        match map.store_assoc_values(&assoc_values, &char_freq) {
+            Ok(()) => Ok(map),
+            Err(e) => Err(e),
+        }
+    }
 
+    /// Sets the range of characters whose associated values are stored in the table, from the
+    /// first to the last that appear in some keysig.
+    const fn set_char_range(&mut self, char_freq: &[u32; 256]) {
+        let mut first = 0;
+        let mut end = 0;
         let mut c = 0;
-        while c <= u8::max_value() as usize {
-            if char_freq[c] == 0 {
-                map.assoc_values[c] = map.max_hash as u16;
+        while c < char_freq.len() {
+            if char_freq[c] > 0 {
+                if end == 0 {
+                    first = c;
+                }
+
+                end = c + 1;
             }
 
             c += 1;
         }
 
-        map
+        self.first_char = first as u8;
+        self.num_chars = end - first;
+    }
+
+    /// Stores the associated values after the slots, in a byte each if every value fits.
+    ///
+    /// A stored value with every bit set marks a character that appears in no keysig.
+    const fn store_assoc_values(
+        &mut self,
+        assoc_values: &[u16; 256],
+        char_freq: &[u32; 256],
+    ) -> Result<(), PhfBuildError> {
+        let mut max_value = 0;
+        iter!((c, value) in assoc_values => {
+            if char_freq[c] > 0 && value > max_value {
+                max_value = value;
+            }
+        });
+
+        self.assoc_width = if max_value < u8::MAX as u16 {
+            1
+        } else if max_value < u16::MAX {
+            2
+        } else {
+            return Err(PhfBuildError::TableOverflow);
+        };
+
+        if self.table_len() > TABLE {
+            return Err(PhfBuildError::TableOverflow);
+        }
+
+        let start = self.assoc_start();
+        let mut i = 0;
+        while i < self.num_chars {
+            let c = self.first_char as usize + i;
+            let value = if char_freq[c] > 0 { assoc_values[c] as usize } else { usize::MAX };
+            self.write_le(start + i * self.assoc_width, self.assoc_width, value);
+            i += 1;
+        }
+
+        Ok(())
     }
 
     const fn hash(&self, key: Key<'_>) -> usize {
+        if let Strategy::Displacement = self.strategy {
+            return self.displaced_hash(key);
+        }
+
+        let mut hash = 0;
+
+        iter!(idx in self.sig.as_slice() => {
+            if let Some(c) = sig::index(key, idx, self.ignore_case) {
+                hash += self.assoc_value(c) as usize;
+            }
+        });
+
+        self.finish_hash(key, hash)
+    }
+
+    /// Like `hash`, but with associated values that have yet to be stored in the table.
+    const fn search_hash(&self, key: Key<'_>, assoc_values: &[u16; 256]) -> usize {
         let mut hash = 0;
 
         iter!(idx in self.sig.as_slice() => {
-            if let Some(c) = sig::index(key, idx) {
-                hash += self.assoc_values[c as usize] as usize;
+            if let Some(c) = sig::index(key, idx, self.ignore_case) {
+                hash += assoc_values[c as usize] as usize;
             }
         });
 
-        hash += key.len();
-        hash
+        self.finish_hash(key, hash)
+    }
+
+    const fn finish_hash(&self, key: Key<'_>, mut hash: usize) -> usize {
+        if self.include_len {
+            hash += key.len();
+        }
+
+        // Only keys that aren't in the map can hash below the offset.
+        hash.saturating_sub(self.hash_offset)
+    }
+
+    /// Returns the associated value of `c`. Characters that appear in no keysig have `max_hash`.
+    pub(crate) const fn assoc_value(&self, c: u8) -> u16 {
+        // Characters before `first_char` wrap around past the end of the range.
+        let i = c.wrapping_sub(self.first_char) as usize;
+        if i >= self.num_chars {
+            return self.max_hash as u16;
+        }
+
+        let width = self.assoc_width;
+        let value = self.read_le(self.assoc_start() + i * width, width);
+        if value == (1 << (8 * width)) - 1 {
+            self.max_hash as u16
+        } else {
+            value as u16
+        }
+    }
+
+    /// The associated values come after the slots in the table.
+    const fn assoc_start(&self) -> usize {
+        (self.max_hash + 1) * self.slot_width()
+    }
+
+    /// Returns the number of bytes actually used by the table.
+    ///
+    /// This is the smallest `TABLE` that can hold this map.
+    pub const fn table_len(&self) -> usize {
+        self.assoc_start() + self.num_chars * self.assoc_width + 2 * self.buckets
+    }
+
+    /// Returns the seed with which the random search found this map.
+    ///
+    /// See `PhfConfig::seed`.
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number of bytes used by each slot in the table.
+    const fn slot_width(&self) -> usize {
+        if self.keys.len() <= MAX_SMALL_KEYS {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Returns the number of slots that fit in the table, leaving a byte for each associated value.
+    const fn slot_capacity(&self) -> usize {
+        TABLE.saturating_sub(self.num_chars) / self.slot_width()
+    }
+
+    /// Returns the number of slots the search may use. Hash values must fit in the table and in a
+    /// `u16`, and the table may be no sparser than `config` allows.
+    const fn max_slots(&self, config: &PhfConfig<'_>) -> usize {
+        let mut ret = self.slot_capacity();
+        if ret > u16::MAX as usize + 1 {
+            ret = u16::MAX as usize + 1;
+        }
+
+        let sparsity_limit = self.keys.len().saturating_mul(config.max_sparsity);
+        if ret > sparsity_limit {
+            ret = sparsity_limit;
+        }
+
+        if let Some(load_limit) = (self.keys.len() * 100).checked_div(config.min_load) {
+            if ret > load_limit {
+                ret = load_limit;
+            }
+        }
+
+        ret
+    }
+
+    /// Returns the index of the key stored at `hash`, or `None` if that slot is empty.
+    const fn slot(&self, hash: usize) -> Option<usize> {
+        let width = self.slot_width();
+        let idx = self.read_le(hash * width, width);
+
+        // An empty slot has every bit set. No key index can look like this, since there are
+        // fewer keys than the largest value that fits in a slot.
+        if idx == (1 << (8 * width)) - 1 {
+            None
+        } else {
+            Some(idx)
+        }
+    }
+
+    const fn set_slot(&mut self, hash: usize, idx: Option<usize>) {
+        let width = self.slot_width();
+        let idx = match idx {
+            Some(idx) => idx,
+            None => usize::MAX,
+        };
+
+        self.write_le(hash * width, width, idx);
+    }
+
+    /// Reads the little-endian integer of `width` bytes at `start` in the table.
+    const fn read_le(&self, start: usize, width: usize) -> usize {
+        let mut ret = 0;
+        let mut i = 0;
+        while i < width {
+            ret |= (self.table[start + i] as usize) << (8 * i);
+            i += 1;
+        }
+
+        ret
+    }
+
+    /// Writes the low `width` bytes of `value` at `start` in the table, little-endian.
+    const fn write_le(&mut self, start: usize, width: usize, value: usize) {
+        let mut i = 0;
+        while i < width {
+            self.table[start + i] = (value >> (8 * i)) as u8;
+            i += 1;
+        }
     }
 
     pub const fn get(&self, key: Key<'_>) -> Option<&T> {
+        match self.get_index(key) {
+            Some(idx) => Some(self.keys.value(idx)),
+            None => None,
+        }
+    }
+
+    /// Returns the position of `key` in the input slice, along with the stored key and value.
+    pub const fn get_entry(&self, key: Key<'_>) -> Option<(usize, Key<'a>, &'a T)> {
+        let keys = self.keys;
+        match self.get_index(key) {
+            Some(idx) => Some((idx, keys.key(idx), keys.value(idx))),
+            None => None,
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the entries in the order they were given.
+    pub fn iter(&self) -> Iter<'a, T> {
+        Iter::new(self.keys)
+    }
+
+    /// Iterates over the entries in the order they appear in the hash table.
+    pub fn iter_hash_order(&self) -> HashOrderIter<'_, 'a, T, TABLE, SIG> {
+        HashOrderIter::new(self)
+    }
+
+    /// Iterates over the keys in the order they were given.
+    pub fn keys(&self) -> impl Iterator<Item = Key<'a>> + 'a {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over the entries in the order they were given, with keys as strings.
+    ///
+    /// # Panics
+    ///
+    /// Panics upon reaching a key that is not valid UTF-8. This cannot happen if the map was built
+    /// with `new_str` or `phf_map!`.
+    pub fn iter_str(&self) -> impl Iterator<Item = (&'a str, &'a T)> + 'a {
+        self.iter().map(|(key, value)| (key_as_str(key), value))
+    }
+
+    /// Iterates over the keys in the order they were given, as strings.
+    ///
+    /// # Panics
+    ///
+    /// Panics upon reaching a key that is not valid UTF-8. This cannot happen if the map was built
+    /// with `new_str` or `phf_map!`.
+    pub fn str_keys(&self) -> impl Iterator<Item = &'a str> + 'a {
+        self.keys().map(key_as_str)
+    }
+
+    /// Iterates over the values in the order they were given.
+    pub fn values(&self) -> impl Iterator<Item = &'a T> + 'a {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub const fn get_str(&self, key: &str) -> Option<&T> {
+        self.get(key.as_bytes())
+    }
+
+    /// Returns the position of `key` in the input slice.
+    ///
+    /// Every key has a distinct index in `0..self.len()`, which can be used to address parallel
+    /// arrays.
+    pub const fn get_index(&self, key: Key<'_>) -> Option<usize> {
+        match self.get_slot(key) {
+            Some((_, idx)) => Some(idx),
+            None => None,
+        }
+    }
+
+    /// Returns the hash value of `key`, which is the slot it occupies in the table.
+    ///
+    /// Every key has a distinct hash value in `0..=self.max_hash()`. In a map built with
+    /// `PhfConfig::minimal`, that is `0..self.len()`, so hash values can be used to address
+    /// parallel arrays in table order without looking up the position of the key.
+    pub const fn get_hash(&self, key: Key<'_>) -> Option<usize> {
+        match self.get_slot(key) {
+            Some((hash, _)) => Some(hash),
+            None => None,
+        }
+    }
+
+    /// Returns the largest hash value of any key.
+    pub const fn max_hash(&self) -> usize {
+        self.max_hash
+    }
+
+    /// Returns the hash value of `key` and its position in the input slice.
+    const fn get_slot(&self, key: Key<'_>) -> Option<(usize, usize)> {
         let hash = self.hash(key);
-        if hash > self.max_hash as usize {
+        if hash > self.max_hash {
             return None;
         }
 
-        let idx = self.table[hash];
-        if idx == SENTINEL {
+        let idx = match self.slot(hash) {
+            Some(idx) => idx,
+            None => return None,
+        };
+
+        if !key_eq(key, self.keys.key(idx), self.ignore_case) {
             return None;
         }
 
-        let (found, ref value) = self.keys[idx as usize];
-        if !key_eq(key, found) {
-            return None;
+        Some((hash, idx))
+    }
+}
+
+impl<'a, T, const TABLE: usize, const SIG: usize> IntoIterator for &PhfMap<'a, T, TABLE, SIG> {
+    type Item = (Key<'a>, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+fn key_as_str(key: Key<'_>) -> &str {
+    std::str::from_utf8(key).expect("key is not valid UTF-8")
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::new(kvs)` will succeed.
+///
+/// This runs the same search as `PhfMap::new`, so it panics if that would. Like the other
+/// `table_len` functions, it assumes the default `SIG`. Use `table_len_with_sig` for any other.
+pub const fn table_len<T>(kvs: &[(Key<'_>, T)]) -> usize {
+    table_len_with_keysig_len(kvs, 0)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::new_str(kvs)` or `phf_map!` will succeed.
+///
+/// ```
+/// use const_phf::{PhfMap, table_len_str};
+///
+/// const KVS: &[(&str, usize)] = &[("if", 0), ("else", 1), ("while", 2)];
+/// const MAP: PhfMap<'static, usize, { table_len_str(KVS) }> = PhfMap::new_str(KVS);
+/// assert_eq!(MAP.get_str("else"), Some(&1));
+/// ```
+pub const fn table_len_str<T>(kvs: &[(&str, T)]) -> usize {
+    let config = PhfConfig::new();
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Str(kvs), Keysigs::Search(0), &config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::with_keysig_len(kvs, len)` will succeed.
+pub const fn table_len_with_keysig_len<T>(kvs: &[(Key<'_>, T)], len: usize) -> usize {
+    let config = PhfConfig::new();
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Search(len), &config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::with_keysig_and_config(kvs, sig, config)` will
+/// succeed. Pass `&PhfConfig::new()` to size a map built with `with_keysig`.
+pub const fn table_len_with_keysig<T>(
+    kvs: &[(Key<'_>, T)],
+    sig: &[isize],
+    config: &PhfConfig<'_>,
+) -> usize {
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Given(sig), config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::with_key_positions_and_config(kvs, spec,
+/// config)` will succeed. Pass `&PhfConfig::new()` to size a map built with `with_key_positions`.
+pub const fn table_len_with_key_positions<T>(
+    kvs: &[(Key<'_>, T)],
+    spec: &str,
+    config: &PhfConfig<'_>,
+) -> usize {
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Positions(spec), config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::with_greedy_keysig(kvs)` will succeed.
+pub const fn table_len_greedy<T>(kvs: &[(Key<'_>, T)]) -> usize {
+    let config = PhfConfig::new().greedy_keysig(true);
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Search(0), &config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::with_backtracking(kvs)` will succeed.
+pub const fn table_len_backtracking<T>(kvs: &[(Key<'_>, T)]) -> usize {
+    let config = PhfConfig::new().backtracking(true);
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Search(0), &config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::new_minimal(kvs)` will succeed.
+pub const fn table_len_minimal<T>(kvs: &[(Key<'_>, T)]) -> usize {
+    let config = PhfConfig::new().minimal().include_len(false);
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Search(0), &config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::with_config(kvs, config)` will succeed.
+pub const fn table_len_with_config<T>(kvs: &[(Key<'_>, T)], config: &PhfConfig<'_>) -> usize {
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Search(0), config)
+}
+
+/// Like `table_len_with_config`, for a map with a keysig of up to `SIG` positions.
+///
+/// ```
+/// use const_phf::{PhfConfig, PhfMap, table_len_with_sig};
+///
+/// const KVS: &[(&[u8], usize)] = &[(b"if", 0), (b"else", 1), (b"while", 2)];
+/// const LEN: usize = table_len_with_sig::<_, 2>(KVS, &PhfConfig::new());
+/// const MAP: PhfMap<'static, usize, LEN, 2> = PhfMap::new(KVS);
+/// assert_eq!(MAP.get(b"else"), Some(&1));
+/// ```
+pub const fn table_len_with_sig<T, const SIG: usize>(
+    kvs: &[(Key<'_>, T)],
+    config: &PhfConfig<'_>,
+) -> usize {
+    search_table_len::<T, SIG>(Entries::Bytes(kvs), Keysigs::Search(0), config)
+}
+
+/// Returns the smallest `TABLE` for which `PhfMap::new_ignore_ascii_case(kvs)` will succeed.
+pub const fn table_len_ignore_ascii_case<T>(kvs: &[(Key<'_>, T)]) -> usize {
+    let config = PhfConfig::new().ignore_ascii_case(true);
+    search_table_len::<T, MAX_KEYSIG_LEN>(Entries::Bytes(kvs), Keysigs::Search(0), &config)
+}
+
+/// Runs the search in a table of `MAX_TABLE_LEN` bytes, and only if that fails in one large enough
+/// for any hash function, which is much slower to evaluate.
+///
+/// A smaller table can only make a search fail sooner, so the map built with the resulting
+/// `TABLE` finds the same hash function as whichever of these succeeded.
+const fn search_table_len<T, const SIG: usize>(
+    kvs: Entries<'_, T>,
+    keysigs: Keysigs<'_>,
+    config: &PhfConfig<'_>,
+) -> usize {
+    if let Ok(map) = PhfMap::<T, MAX_TABLE_LEN, SIG>::build_for(kvs, keysigs, config) {
+        return map.table_len();
+    }
+
+    match PhfMap::<T, SEARCH_TABLE_LEN, SIG>::build_for(kvs, keysigs, config) {
+        Ok(map) => map.table_len(),
+        Err(e) => e.panic_with(kvs),
+    }
+}
+
+/// Where the keysig of a map comes from, so that `search_table_len` can size a map built by any
+/// constructor.
+#[derive(Clone, Copy)]
+enum Keysigs<'s> {
+    /// Searched for, starting with keysigs of the given length, as by `with_keysig_len`.
+    Search(usize),
+
+    /// The positions given to `with_keysig`.
+    Given(&'s [isize]),
+
+    /// The key positions given to `with_key_positions`.
+    Positions(&'s str),
+}
+
+/// Checks that there are at most `MAX_KEYS` keys, and that no key appears twice.
+const fn check_keys<T>(kvs: Entries<'_, T>, ignore_case: bool) -> Result<(), PhfBuildError> {
+    if kvs.len() > MAX_KEYS {
+        return Err(PhfBuildError::TooManyKeys);
+    }
+
+    match find_duplicate_keys(kvs, ignore_case) {
+        Some((first, second)) => Err(PhfBuildError::DuplicateKeys { first, second }),
+        None => Ok(()),
+    }
+}
+
+const fn count_char_frequency<T>(
+    kvs: Entries<'_, T>,
+    sig: &[isize],
+    ignore_case: bool,
+) -> [u32; 256] {
+    let mut char_freq = [0; 256];
+
+    iter_keys!(key in kvs => {
+        iter!(idx in sig => {
+            if let Some(c) = sig::index(key, idx, ignore_case) {
+                char_freq[c as usize] += 1;
+            }
+        });
+    });
+
+    char_freq
+}
+
+/// Searches for associated values under which no two keys collide, placing keys in the table in
+/// the given order.
+///
+/// Each of `config.attempts` seeds is tried in turn, starting from `config.seed`, until one of
+/// them works.
+const fn find_assoc_values_random<T, const TABLE: usize, const SIG: usize>(
+    map: &mut PhfMap<'_, T, TABLE, SIG>,
+    assoc_values: &mut [u16; 256],
+    order: &[u16],
+    char_freq: &[u32; 256],
+    config: &PhfConfig<'_>,
+) -> Result<(), PhfBuildError> {
+    let mut attempt = 0;
+    loop {
+        let seed = config.seed.wrapping_add(attempt as u64);
+        match find_assoc_values_with_seed(map, assoc_values, order, char_freq, config, seed) {
+            Ok(()) => {
+                map.seed = seed;
+                return Ok(());
+            }
+
+            Err(PhfBuildError::SearchExhausted) | Err(PhfBuildError::TableOverflow)
+                if attempt + 1 < config.attempts => {}
+
+            Err(e) => return Err(e),
+        }
+
+        map.table = [SENTINEL; TABLE];
+        *assoc_values = [0; 256];
+        attempt += 1;
+    }
+}
+
+/// One attempt of `find_assoc_values_random`. After a collision, one of the associated values is
+/// raised by an amount chosen at random, and the search starts over.
+///
+/// Keys placed before the first one whose keysig contains that character keep their slots, since
+/// their hash values are the same as before, so the search picks up from that key.
+const fn find_assoc_values_with_seed<T, const TABLE: usize, const SIG: usize>(
+    map: &mut PhfMap<'_, T, TABLE, SIG>,
+    assoc_values: &mut [u16; 256],
+    order: &[u16],
+    char_freq: &[u32; 256],
+    config: &PhfConfig<'_>,
+    seed: u64,
+) -> Result<(), PhfBuildError> {
+    let increments = config.increments;
+    let slots = map.max_slots(config);
+    let mut rng = Rng::new(seed);
+
+    // The position in `order` of the first key whose keysig contains each character.
+    let mut first_use = [usize::MAX; 256];
+    iter!((k, i) in order => {
+        let key = map.keys.key(i as usize);
+        for_each_char_in_keysig!(key, map.sig.as_slice(), map.ignore_case, |c| {
+            if first_use[c as usize] > k {
+                first_use[c as usize] = k;
+            }
+        });
+    });
+
+    // The slot of the key at each position in `order` that has been placed. There are no more keys
+    // than `TABLE`.
+    let mut slots_taken = [0u32; TABLE];
+
+    let mut tries = 0;
+    let mut k = 0;
+    while k < order.len() {
+        let i = order[k] as usize;
+        let key = map.keys.key(i);
+        let hash = map.search_hash(key, assoc_values);
+        if hash >= slots {
+            return Err(PhfBuildError::TableOverflow);
+        }
+
+        let other_idx = match map.slot(hash) {
+            None => {
+                map.set_slot(hash, Some(i));
+                slots_taken[k] = hash as u32;
+                k += 1;
+                continue;
+            }
+
+            Some(idx) => idx,
+        };
+
+        // Collision with `other`
+        let other = map.keys.key(other_idx);
+        let to_incr =
+            rarest_differing_char(key, other, map.sig.as_slice(), map.ignore_case, char_freq);
+
+        let to_incr = if let Some(c) = to_incr {
+            c
+        } else {
+            let (first, second) = if other_idx < i { (other_idx, i) } else { (i, other_idx) };
+            return Err(no_unique_keysig(first, second, map.sig.as_slice()));
+        };
+
+        // Clear the slots of the keys that will move, and of every key placed after them.
+        let start = if first_use[to_incr as usize] < k { first_use[to_incr as usize] } else { k };
+        while k > start {
+            k -= 1;
+            map.set_slot(slots_taken[k] as usize, None);
+        }
+
+        // Update associated values array
+        let incr = increments[rng.below(increments.len())];
+        if assoc_values[to_incr as usize] > u16::MAX - incr {
+            return Err(PhfBuildError::TableOverflow);
+        }
+
+        assoc_values[to_incr as usize] += incr;
+
+        tries += 1;
+        if tries > config.max_tries {
+            return Err(PhfBuildError::SearchExhausted);
+        }
+    }
+
+    Ok(())
+}
+
+const fn freq_score(
+    key: Key<'_>,
+    sig: &[isize],
+    ignore_case: bool,
+    char_freq: &[u32; 256],
+) -> isize {
+    let mut ret = 0;
+    for_each_char_in_keysig!(key, sig, ignore_case, |c| {
+        ret += char_freq[c as usize] as isize;
+    });
+
+    ret
+}
+
+/// Returns the rarest character that appears more times in the keysig of one key than in that of
+/// the other, so that raising its associated value moves one key and not the other. Ties go to
+/// the character that comes first in the keysig of `a`, then in that of `b`.
+///
+/// This runs after every collision, so it counts characters in place rather than building both
+/// keysigs.
+const fn rarest_differing_char(
+    a: Key<'_>,
+    b: Key<'_>,
+    sig: &[isize],
+    ignore_case: bool,
+    char_freq: &[u32; 256],
+) -> Option<u8> {
+    // How many more times each character appears in the keysig of `a` than in that of `b`.
+    let mut diff = [0isize; 256];
+    for_each_char_in_keysig!(a, sig, ignore_case, |c| {
+        diff[c as usize] += 1;
+    });
+
+    for_each_char_in_keysig!(b, sig, ignore_case, |c| {
+        diff[c as usize] -= 1;
+    });
+
+    let mut rarest_char = None;
+    let mut rarest_freq = u32::MAX;
+    let keys = [a, b];
+    let mut k = 0;
+    while k < keys.len() {
+        for_each_char_in_keysig!(keys[k], sig, ignore_case, |c| {
+            if diff[c as usize] != 0 && char_freq[c as usize] < rarest_freq {
+                rarest_char = Some(c);
+                rarest_freq = char_freq[c as usize];
+            }
+        });
+
+        k += 1;
+    }
+
+    rarest_char
+}
+
+/// Orders the keys for `find_assoc_values_random`, the way gperf does.
+///
+/// Keys whose keysigs contain the most common characters come first. After each key, any later
+/// keys whose keysigs consist only of characters seen so far are moved up, since their hash values
+/// depend only on characters that earlier keys already use. A collision with one of them is found
+/// right away, before the search has placed keys that will have to be placed again.
+const fn order_keywords<T, const TABLE: usize>(
+    order: &mut [u16],
+    kvs: Entries<'_, T>,
+    sig: &[isize],
+    ignore_case: bool,
+    char_freq: &[u32; 256],
+) {
+    // The score of each key is computed once rather than at every comparison. There are no more
+    // keys than `TABLE`.
+    let mut scores = [0u32; TABLE];
+    iter!((i, _) in order => {
+        order[i] = i as u16;
+        scores[i] = freq_score(kvs.key(i), sig, ignore_case, char_freq) as u32;
+    });
+
+    // Sort keys containing more commonly used characters near the start
+    sort_by_key!(order, |i| -(scores[i as usize] as i64));
+
+    let mut settled_chars = [false; 256];
+
+    let mut i = 0;
+    while i < order.len() {
+        // Mark all characters in `kvs[order[i]]` as settled
+        for_each_char_in_keysig!(kvs.key(order[i] as usize), sig, ignore_case, |c| {
+            settled_chars[c as usize] = true;
+        });
+
+        i += 1;
+
+        // Look for keys whose keysig consists of all settled characters
+        let mut j = i;
+        while j < order.len() {
+            if !is_keysig_settled(kvs.key(order[j] as usize), sig, ignore_case, &settled_chars) {
+                j += 1;
+                continue;
+            }
+
+            // Found one at index `j`. Move it to index `i`, shifting the ones in between back.
+            let to_move = order[j];
+            let mut k = j;
+            while k > i {
+                order[k] = order[k - 1];
+                k -= 1;
+            }
+
+            order[i] = to_move;
+            i += 1;
+            j += 1;
+        }
+    }
+}
+
+const fn is_keysig_settled(
+    key: Key<'_>,
+    sig: &[isize],
+    ignore_case: bool,
+    settled_chars: &[bool; 256],
+) -> bool {
+    for_each_char_in_keysig!(key, sig, ignore_case, |c| {
+        if !settled_chars[c as usize] {
+            return false;
         }
+    });
+
+    true
+}
+
+/// The most assignments of associated values `find_assoc_values_backtracking` will try.
+const MAX_BACKTRACKING_STEPS: usize = 1_000_000;
+
+/// A set of characters, by their index in the order `find_assoc_values_backtracking` assigns them.
+type CharSet = [u64; 4];
+
+/// Searches for associated values by trying every assignment in turn, the way gperf does.
+///
+/// Characters get values in the order they first appear in the keysigs of `order`, counting up
+/// from zero. A key is placed in the table as soon as every character in its keysig has a value.
+/// If its slot is taken, the character that was assigned last gets the next value. Raising a
+/// value never lowers a hash, so a character has no more values to try once a key containing it
+/// no longer fits in the table.
+///
+/// When a character runs out of values, the search backtracks to the last character assigned
+/// before it that was part of a failure, since changing any character in between can't help.
+/// This is still exhaustive, so if it fails with `NoSolution`, there is no solution at all.
+const fn find_assoc_values_backtracking<T, const TABLE: usize, const SIG: usize>(
+    map: &mut PhfMap<'_, T, TABLE, SIG>,
+    assoc_values: &mut [u16; 256],
+    order: &[u16],
+    config: &PhfConfig<'_>,
+) -> Result<(), PhfBuildError> {
+    const UNASSIGNED: usize = usize::MAX;
+
+    let slots = map.max_slots(config);
+
+    // The characters in the order they are assigned, the index of each in that order, and the
+    // first key whose keysig contains each.
+    let mut chars = [0u8; 256];
+    let mut char_idx = [UNASSIGNED; 256];
+    let mut first_key = [0u16; 256];
+    let mut num_chars = 0;
+
+    // The keys whose keysig is complete once `chars[n]` is assigned are in group `n + 1`. Keys
+    // with no characters in their keysig are in group 0. There are no more keys than `TABLE`.
+    let mut group_of = [0u16; TABLE];
+    let mut group_start = [0; 258];
+    iter!(i in order => {
+        let mut group = 0;
+        let key = map.keys.key(i as usize);
+        for_each_char_in_keysig!(key, map.sig.as_slice(), map.ignore_case, |c| {
+            if char_idx[c as usize] == UNASSIGNED {
+                chars[num_chars] = c;
+                char_idx[c as usize] = num_chars;
+                first_key[num_chars] = i;
+                num_chars += 1;
+            }
+
+            if char_idx[c as usize] + 1 > group {
+                group = char_idx[c as usize] + 1;
+            }
+        });
+
+        group_of[i as usize] = group as u16;
+        group_start[group + 1] += 1;
+    });
+
+    let mut group = 1;
+    while group < group_start.len() {
+        group_start[group] += group_start[group - 1];
+        group += 1;
+    }
+
+    let mut by_group = [0u16; TABLE];
+    let mut next = group_start;
+    iter!(i in order => {
+        let group = group_of[i as usize] as usize;
+        by_group[next[group]] = i;
+        next[group] += 1;
+    });
+
+    // Keys in group 0 have fixed hash values.
+    let placed = place_keys(map, assoc_values, &by_group, group_start[0], group_start[1], slots);
+    if !placed.is_ok() {
+        return Err(PhfBuildError::NoSolution);
+    }
+
+    // The characters assigned before `chars[n]` that were part of a failure of one of its values.
+    let mut conflicts = [[0; 4]; 256];
+
+    let mut n = 0;
+    let mut steps = 0;
+    while n < num_chars {
+        steps += 1;
+        if steps > MAX_BACKTRACKING_STEPS {
+            return Err(PhfBuildError::SearchExhausted);
+        }
+
+        // Whether every larger value fails as well, and the keys responsible for the failure.
+        let c = chars[n] as usize;
+        let first = first_key[n] as usize;
+        let (start, end) = (group_start[n + 1], group_start[n + 2]);
+        let (exhausted, a, b) = if map.search_hash(map.keys.key(first), assoc_values) >= slots {
+            (true, first, first)
+        } else {
+            match place_keys(map, assoc_values, &by_group, start, end, slots) {
+                Placement::Placed if has_room(map, assoc_values, &by_group, end, slots) => {
+                    n += 1;
+                    continue;
+                }
 
-        Some(value)
-    }
-}
+                // Too few slots are left for the remaining keys. That depends on the values of
+                // every character so far. A larger value might move these keys out of the way,
+                // so it's still worth trying.
+                Placement::Placed => {
+                    remove_keys(map, assoc_values, &by_group, start, end);
+                    let mut m = 0;
+                    while m < n {
+                        conflicts[n][m / 64] |= 1 << (m % 64);
+                        m += 1;
+                    }
+
+                    (false, first, first)
+                }
 
-const fn count_char_frequency(keys: &[Key<'_>], sig: &[isize]) -> [u8; 256] {
-    let mut char_freq = [0; 256];
+                // If both keys contain `chars[n]` the same number of times, no value of it can
+                // separate them.
+                Placement::Collision(i, other) => {
+                    let same = group_of[other] as usize == n + 1
+                        && count_char(map, i, chars[n]) == count_char(map, other, chars[n]);
+                    (same, i, other)
+                }
 
-    iter!(ref key in keys => {
-        iter!(idx in sig => {
-            if let Some(c) = sig::index(key, idx) {
-                char_freq[c as usize] += 1;
+                Placement::Overflow(i) => (true, i, i),
             }
-        });
-    });
+        };
 
-    char_freq
-}
+        add_conflicts(&mut conflicts[n], map, a, &char_idx, n);
+        add_conflicts(&mut conflicts[n], map, b, &char_idx, n);
 
-const fn find_assoc_values_random<T>(
-    map: &mut PhfMap<'_, T>,
-    char_freq: &[u8; 256],
-) -> Result<(), &'static str> {
-    // Best by test.
-    const INCREMENTS: [u16; 3] = [1, 3, 4];
-    const MAX_TRIES: usize = 10_000;
+        // The first key containing `chars[n]` still fits in the table, so this can't overflow.
+        if !exhausted {
+            assoc_values[c] += 1;
+            continue;
+        }
 
-    let mut n = 0;
-    'retry: while n <= MAX_TRIES {
-        n += 1;
+        let conflict = conflicts[n];
+        assoc_values[c] = 0;
+        conflicts[n] = [0; 4];
 
-        iter!((i, ref key) in map.keys => {
-            let hash = map.hash(key.0);
-            if hash >= map.table.len() {
-                return Err("Failed to find perfect hash");
-            }
+        let back = match last_char(&conflict) {
+            Some(back) => back,
+            None => return Err(PhfBuildError::NoSolution),
+        };
 
-            if map.table[hash] == SENTINEL {
-                map.table[hash] = i as u8;
-                continue;
+        // Undo every character after `chars[back]`, and the keys placed for `chars[back]` itself.
+        while n > back {
+            n -= 1;
+            remove_keys(map, assoc_values, &by_group, group_start[n + 1], group_start[n + 2]);
+            if n > back {
+                assoc_values[chars[n] as usize] = 0;
+                conflicts[n] = [0; 4];
             }
+        }
 
-            // Collision with `other`
-            let other = &map.keys[map.table[hash] as usize];
-            let to_incr = rarest_char_in_disjoint_union(
-                &keysig(key.0, map.sig.as_slice()),
-                &keysig(other.0, map.sig.as_slice()),
-                char_freq,
-            );
-
-            let to_incr = if let Some(c) = to_incr {
-                c
-            } else {
-                return Err("duplicate keysigs");
-            };
-
-            // Clear the table
-            map.table = [SENTINEL; MAX_TABLE_LEN];
-
-            // Update associated values array
-            let incr = INCREMENTS[n % INCREMENTS.len()];
-            map.assoc_values[to_incr as usize] += incr;
-            continue 'retry;
-        });
+        // Whatever made `chars[n]` fail depends on the characters before `chars[back]` as well.
+        let mut w = 0;
+        while w < conflict.len() {
+            conflicts[back][w] |= conflict[w];
+            w += 1;
+        }
 
-        return Ok(());
+        conflicts[back][back / 64] &= !(1 << (back % 64));
+        assoc_values[chars[back] as usize] += 1;
     }
 
-    Err("Failed to find perfect hash")
+    Ok(())
 }
 
-const fn freq_score(key: Key<'_>, sig: &[isize], char_freq: &[u8; 256]) -> isize {
-    let mut ret = 0;
-    for_each_char_in_keysig!(key, sig, |c| {
-        ret += char_freq[c as usize] as isize;
+/// Adds the characters in the keysig of the key at index `i` that were assigned before `chars[n]`.
+const fn add_conflicts<T, const TABLE: usize, const SIG: usize>(
+    set: &mut CharSet,
+    map: &PhfMap<'_, T, TABLE, SIG>,
+    i: usize,
+    char_idx: &[usize; 256],
+    n: usize,
+) {
+    for_each_char_in_keysig!(map.keys.key(i), map.sig.as_slice(), map.ignore_case, |c| {
+        let m = char_idx[c as usize];
+        if m < n {
+            set[m / 64] |= 1 << (m % 64);
+        }
     });
-
-    ret
 }
 
-/*
-const fn order_keywords(
-    keys: &mut [Key<'_>],
-    sig: &[isize],
-    char_freq: &[u8; 256],
-) {
-    // Sort keys containing more commonly used characters near the start
-    sort_by_key!(keys, |key| -freq_score(key, sig, char_freq));
+/// Returns the character in `set` that was assigned last.
+const fn last_char(set: &CharSet) -> Option<usize> {
+    let mut w = set.len();
+    while w > 0 {
+        w -= 1;
+        if set[w] != 0 {
+            return Some(w * 64 + 63 - set[w].leading_zeros() as usize);
+        }
+    }
 
-    let mut settled_chars = [false; 256];
+    None
+}
 
-    let mut i = 0;
-    while i < keys.len() {
-        // Mark all characters in `keys[i]` as settled
-        for_each_char_in_keysig!(keys[i], sig, |c| {
-            settled_chars[c as usize] = true;
-        });
+/// The result of placing a group of keys in the table.
+#[derive(Clone, Copy)]
+enum Placement {
+    Placed,
 
-        i += 1;
+    /// The key at the first index hashed to the slot of the key at the second.
+    Collision(usize, usize),
 
-        // Look for keys whose keysig consists of all settled characters
-        let mut j = i;
-        while j < keys.len() {
-            if !is_keysig_settled(keys[j], sig, &settled_chars) {
-                continue;
-            }
+    /// The hash of the key at this index didn't fit in the table.
+    Overflow(usize),
+}
 
-            // Found one at index `j`.
-            let to_move = keys[j];
-            keys.remove(j);
-            keys.insert(i, to_move);
-            i += 1;
-            j += 1;
-        }
+impl Placement {
+    const fn is_ok(&self) -> bool {
+        matches!(self, Placement::Placed)
     }
 }
 
-const fn is_keysig_settled(key: Key<'_>, sig: &[isize], settled_chars: &[bool; 256]) -> bool {
-    for_each_char_in_keysig!(key, sig, |c| {
-        if !settled_chars[c as usize] {
-            return false;
+/// Places the keys at `by_group[start..end]` in the table. If one can't be placed, none are.
+const fn place_keys<T, const TABLE: usize, const SIG: usize>(
+    map: &mut PhfMap<'_, T, TABLE, SIG>,
+    assoc_values: &[u16; 256],
+    by_group: &[u16],
+    start: usize,
+    end: usize,
+    slots: usize,
+) -> Placement {
+    let mut j = start;
+    while j < end {
+        let i = by_group[j] as usize;
+        let hash = map.search_hash(map.keys.key(i), assoc_values);
+        let res = if hash >= slots {
+            Placement::Overflow(i)
+        } else if let Some(other) = map.slot(hash) {
+            Placement::Collision(i, other)
+        } else {
+            Placement::Placed
+        };
+
+        if !res.is_ok() {
+            remove_keys(map, assoc_values, by_group, start, j);
+            return res;
         }
-    });
 
-    true
+        map.set_slot(hash, Some(i));
+        j += 1;
+    }
+
+    Placement::Placed
 }
 
-const fn find_assoc_values(
-    keys: &[Key<'a>],
-    sig: &[isize],
-    table: &mut [u8; MAX_TABLE_LEN],
-    assoc_values: &mut [u16; 256],
-    settled: &mut [u8; 256],
-    i: usize,
-) -> Result<(), &'static str> {
-    let max_assoc_value = keys.len();
-    let key = keys[i];
+/// Returns whether there are enough empty slots for the keys at `by_group[start..]`.
+///
+/// The hash of each of those keys can only grow as more characters are assigned, so a slot lower
+/// than all of their current hashes will never be filled.
+const fn has_room<T, const TABLE: usize, const SIG: usize>(
+    map: &PhfMap<'_, T, TABLE, SIG>,
+    assoc_values: &[u16; 256],
+    by_group: &[u16],
+    start: usize,
+    slots: usize,
+) -> bool {
+    let end = map.keys.len();
+    let mut lowest = usize::MAX;
+    let mut j = start;
+    while j < end {
+        let hash = map.search_hash(map.keys.key(by_group[j] as usize), assoc_values);
+        if hash < lowest {
+            lowest = hash;
+        }
 
-    loop {
-        for_each_char_in_keysig!(key, sig, |c| {
-            assoc_values[c] += 1;
-            if assoc_values[c] > max_assoc_value {
-                return Err(());
-            }
+        j += 1;
+    }
 
-            let mut j = i + 1;
-            while j < keys.len() {
-                if
+    let mut empty = 0;
+    let mut hash = lowest;
+    while empty < end - start && hash < slots {
+        if map.slot(hash).is_none() {
+            empty += 1;
+        }
 
+        hash += 1;
+    }
 
-                let hash = hash(keys[j], sig, assoc_values);
-                if hash > table.len() {
-                    return Err(());
-                }
+    empty == end - start
+}
 
-                if table[hash] != SENTINEL {
-                    return Err(());
-                }
+/// Returns the number of times `c` appears in the keysig of the key at index `i`.
+const fn count_char<T, const TABLE: usize, const SIG: usize>(
+    map: &PhfMap<'_, T, TABLE, SIG>,
+    i: usize,
+    c: u8,
+) -> usize {
+    let mut ret = 0;
+    for_each_char_in_keysig!(map.keys.key(i), map.sig.as_slice(), map.ignore_case, |d| {
+        if d == c {
+            ret += 1;
+        }
+    });
 
-                table[hash] = j as u8;
-            }
-        });
-    }
+    ret
+}
 
-    Ok(())
+/// Removes the keys at `by_group[start..end]` from the table.
+const fn remove_keys<T, const TABLE: usize, const SIG: usize>(
+    map: &mut PhfMap<'_, T, TABLE, SIG>,
+    assoc_values: &[u16; 256],
+    by_group: &[u16],
+    start: usize,
+    end: usize,
+) {
+    let mut j = start;
+    while j < end {
+        let hash = map.search_hash(map.keys.key(by_group[j] as usize), assoc_values);
+        map.set_slot(hash, None);
+        j += 1;
+    }
 }
-*/
 
 #[cfg(test)]
 mod tests {
@@ -369,4 +1626,680 @@ mod tests {
         assert_eq!(PHF.get(b"whoo").copied(), None);
         assert_eq!(PHF.get(b"tipe").copied(), None);
     }
+
+    #[test]
+    fn try_new() {
+        const DUPLICATE: Result<PhfMap<'static, ()>, PhfBuildError> = PhfMap::try_new(&[
+            (b"fn", ()),
+            (b"let", ()),
+            (b"fn", ()),
+        ]);
+
+        const NO_KEYSIG: Result<PhfMap<'static, ()>, PhfBuildError> = PhfMap::try_with_keysig(&[
+            (b"ab", ()),
+            (b"ba", ()),
+        ], &[0, 1]);
+
+        assert_eq!(DUPLICATE.err(), Some(PhfBuildError::DuplicateKeys { first: 0, second: 2 }));
+
+        match NO_KEYSIG {
+            Err(PhfBuildError::NoUniqueKeysig { first: 0, second: 1, sig, truncated: false }) => {
+                assert_eq!(sig.as_slice(), &[0, 1]);
+            }
+
+            _ => panic!("expected keysig collision"),
+        }
+
+        let msg = DUPLICATE.err().unwrap().to_string();
+        assert_eq!(msg, "duplicate keys at indices 0 and 2");
+    }
+
+    #[test]
+    fn key_positions() {
+        // These only differ in the middle, beyond the positions the search tries first.
+        const KVS: &[(Key<'static>, u16)] = &[
+            (b"ERROR_1040_CODE", 1040),
+            (b"ERROR_1042_CODE", 1042),
+            (b"ERROR_1137_CODE", 1137),
+            (b"ERROR_2042_CODE", 2042),
+        ];
+
+        const SEARCHED: PhfMap<'static, u16> = PhfMap::new(KVS);
+        const GIVEN: PhfMap<'static, u16> = PhfMap::with_key_positions(KVS, "7-10,$");
+
+        assert_eq!(GIVEN.sig.as_slice(), &[6, 7, 8, 9, -1]);
+        for map in &[SEARCHED, GIVEN] {
+            for (key, value) in KVS {
+                assert_eq!(map.get(key), Some(value));
+            }
+
+            assert_eq!(map.get(b"ERROR_1041_CODE"), None);
+        }
+
+        match PhfMap::<'_, u16>::try_with_key_positions(KVS, "1,$") {
+            Err(PhfBuildError::NoUniqueKeysig { first: 0, second: 1, sig, truncated: false }) => {
+                assert_eq!(sig.as_slice(), &[0, -1]);
+            }
+
+            _ => panic!("expected keysig collision"),
+        }
+    }
+
+    #[test]
+    fn large_keysig() {
+        // Each key differs from the first in a different one of nine positions, so every one of
+        // those must be in the keysig.
+        const KVS: &[(Key<'static>, usize)] = &[
+            (b"xxxxxxxxxx", 0),
+            (b"axxxxxxxxx", 1),
+            (b"xbxxxxxxxx", 2),
+            (b"xxcxxxxxxx", 3),
+            (b"xxxdxxxxxx", 4),
+            (b"xxxxexxxxx", 5),
+            (b"xxxxxfxxxx", 6),
+            (b"xxxxxxgxxx", 7),
+            (b"xxxxxxxhxx", 8),
+            (b"xxxxxxxxix", 9),
+        ];
+
+        const LEN: usize = table_len_with_sig::<_, 12>(KVS, &PhfConfig::new());
+        const MAP: PhfMap<'static, usize, LEN, 12> = PhfMap::new(KVS);
+
+        assert_eq!(MAP.sig.len(), 9);
+        for (key, value) in KVS {
+            assert_eq!(MAP.get(key), Some(value));
+        }
+
+        assert_eq!(MAP.get(b"xxxxxxxxxa"), None);
+        assert!(matches!(
+            PhfMap::<'_, usize>::try_new(KVS),
+            Err(PhfBuildError::NoUniqueKeysig { .. }),
+        ));
+        assert_eq!(
+            PhfMap::<'_, usize, MAX_TABLE_LEN, 2>::try_with_key_positions(KVS, "1-3").err(),
+            Some(PhfBuildError::KeysigTooLong),
+        );
+
+        // A keysig of nine positions can't fit in a map whose keysigs hold seven, even though a
+        // shorter one would work.
+        let kvs: &[(Key<'_>, ())] = &[(b"ab", ()), (b"ba", ()), (b"cc", ())];
+        assert!(PhfMap::<'_, (), MAX_TABLE_LEN, 7>::try_with_keysig_len(kvs, 2).is_ok());
+        assert_eq!(
+            PhfMap::<'_, (), MAX_TABLE_LEN, 7>::try_with_keysig_len(kvs, 9).err(),
+            Some(PhfBuildError::KeysigTooLong),
+        );
+
+        // The last key only differs from the first at the ninth position.
+        match PhfMap::<'_, usize, MAX_TABLE_LEN, 12>::try_with_key_positions(KVS, "1-8") {
+            Err(e @ PhfBuildError::NoUniqueKeysig { first, second, sig, truncated: true }) => {
+                assert_eq!((first, second), (0, 9));
+                assert_eq!(sig.as_slice(), &[0, 1, 2, 3, 4, 5, 6]);
+                assert!(e.to_string().ends_with("[0, 1, 2, 3, 4, 5, 6, ...]"));
+            }
+
+            _ => panic!("expected keysig collision"),
+        }
+    }
+
+    /// Returns `n` distinct pseudo-random lowercase words.
+    pub(crate) fn random_words(n: usize) -> Vec<Vec<u8>> {
+        let mut state = 1u32;
+        let mut next = || {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) as usize
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        while keys.len() < n {
+            let len = 3 + next() % 6;
+            let key: Vec<u8> = (0..len).map(|_| b'a' + (next() % 26) as u8).collect();
+            if seen.insert(key.clone()) {
+                keys.push(key);
+            }
+        }
+
+        keys
+    }
+
+    /// The number of words in `RANDOM_WORDS`.
+    const RANDOM_WORDS_LEN: usize = 255;
+
+    /// Distinct pseudo-random words, padded with NUL. The length of each depends on its index.
+    static RANDOM_WORDS: [[u8; 8]; RANDOM_WORDS_LEN] = {
+        let mut words = [[0; 8]; RANDOM_WORDS_LEN];
+        let mut state = 1u32;
+        let mut n = 0;
+        'next: while n < RANDOM_WORDS_LEN {
+            let len = 4 + n % 5;
+            let mut word = [0; 8];
+            let mut i = 0;
+            while i < len {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                word[i] = b'a' + ((state >> 16) % 26) as u8;
+                i += 1;
+            }
+
+            let mut j = 0;
+            while j < n {
+                if key_eq(&words[j], &word, false) {
+                    continue 'next;
+                }
+
+                j += 1;
+            }
+
+            words[n] = word;
+            n += 1;
+        }
+
+        words
+    };
+
+    /// `RANDOM_WORDS`, of four to eight letters each, with their positions as their values. Unlike
+    /// `random_words`, this can be used in a `const`.
+    pub(crate) static RANDOM_KVS: [(Key<'static>, u32); RANDOM_WORDS_LEN] = {
+        let mut kvs: [(Key<'static>, u32); RANDOM_WORDS_LEN] = [(b"", 0); RANDOM_WORDS_LEN];
+        let mut i = 0;
+        while i < RANDOM_WORDS_LEN {
+            kvs[i] = (RANDOM_WORDS[i].split_at(4 + i % 5).0, i as u32);
+            i += 1;
+        }
+
+        kvs
+    };
+
+    /// The number of words in `WORDS`.
+    const WORDS_LEN: usize = 3000;
+
+    /// Pseudo-random words, padded with NUL. The first three letters spell the index of each word
+    /// in base 26, so that no two are the same.
+    static WORDS: [[u8; 8]; WORDS_LEN] = {
+        let mut words = [[0; 8]; WORDS_LEN];
+        let mut state = 1u32;
+        let mut n = 0;
+        while n < WORDS_LEN {
+            words[n][0] = b'a' + (n % 26) as u8;
+            words[n][1] = b'a' + (n / 26 % 26) as u8;
+            words[n][2] = b'a' + (n / 676) as u8;
+            let mut i = 3;
+            while i < 8 {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                words[n][i] = b'a' + ((state >> 16) % 26) as u8;
+                i += 1;
+            }
+
+            n += 1;
+        }
+
+        words
+    };
+
+    /// Returns the first `N` words of `WORDS`, of four to eight letters each, with their positions
+    /// as their values. Like `RANDOM_KVS`, this can be used in a `const`.
+    pub(crate) const fn const_words<const N: usize>() -> [(Key<'static>, u32); N] {
+        let mut kvs: [(Key<'static>, u32); N] = [(b"", 0); N];
+        let mut i = 0;
+        while i < N {
+            kvs[i] = (WORDS[i].split_at(4 + i % 5).0, i as u32);
+            i += 1;
+        }
+
+        kvs
+    }
+
+    /// A few SQL keywords, each with its position as its value.
+    pub(crate) const SQL_KEYWORDS: &[(Key<'static>, usize)] = &[
+        (b"select", 0),
+        (b"from", 1),
+        (b"where", 2),
+        (b"group", 3),
+        (b"order", 4),
+        (b"by", 5),
+        (b"having", 6),
+        (b"limit", 7),
+        (b"insert", 8),
+        (b"update", 9),
+        (b"delete", 10),
+        (b"join", 11),
+    ];
+
+    #[test]
+    fn greedy_keysig() {
+        const KVS: &[(Key<'static>, usize)] = &[
+            (b"as", 1),
+            (b"break", 2),
+            (b"const", 3),
+            (b"continue", 4),
+            (b"crate", 5),
+            (b"else", 6),
+            (b"enum", 7),
+            (b"extern", 8),
+            (b"false", 9),
+            (b"fn", 10),
+            (b"for", 11),
+            (b"if", 12),
+            (b"impl", 13),
+            (b"in", 14),
+            (b"let", 15),
+            (b"loop", 16),
+            (b"match", 17),
+            (b"mod", 18),
+        ];
+
+        const PHF: PhfMap<'static, usize, { table_len_greedy(KVS) }> =
+            PhfMap::with_greedy_keysig(KVS);
+
+        for (key, value) in KVS {
+            assert_eq!(PHF.get(key), Some(value));
+        }
+
+        assert_eq!(PHF.get(b"mud"), None);
+
+        const MANY: &[(Key<'static>, u32)] = RANDOM_KVS.split_at(150).0;
+        const MANY_PHF: PhfMap<'static, u32, 8192> = PhfMap::with_greedy_keysig(MANY);
+        for (key, value) in MANY {
+            assert_eq!(MANY_PHF.get(key), Some(value));
+        }
+
+        // Too many to build in a `const`.
+        let keys = random_words(300);
+        let kvs: Vec<(Key<'_>, ())> = keys.iter().map(|key| (key.as_slice(), ())).collect();
+        let map: PhfMap<'_, (), 8192> = PhfMap::try_with_greedy_keysig(&kvs).unwrap();
+        assert!(kvs.iter().all(|(key, _)| map.get(key).is_some()));
+    }
+
+    #[test]
+    fn backtracking() {
+        const KVS: &[(Key<'static>, usize)] = SQL_KEYWORDS;
+
+        const LEN: usize = crate::table_len_backtracking(KVS);
+        const MAP: PhfMap<'static, usize, LEN> = PhfMap::with_backtracking(KVS);
+
+        for (key, value) in KVS {
+            assert_eq!(MAP.get(key), Some(value));
+        }
+
+        // The random search may need a few seeds to fit the keys in so small a table. Every seed
+        // before the one it reports fails, and the one it reports rebuilds the same map.
+        let config = PhfConfig::new().attempts(100);
+        let reseeded = PhfMap::<'_, usize, LEN>::try_with_config(KVS, &config).unwrap();
+        for seed in 0..reseeded.seed() {
+            let config = config.seed(seed).attempts(1);
+            assert!(PhfMap::<'_, usize, LEN>::try_with_config(KVS, &config).is_err());
+        }
+
+        let config = config.seed(reseeded.seed()).attempts(1);
+        let reproduced = PhfMap::<'_, usize, LEN>::try_with_config(KVS, &config).unwrap();
+        assert_eq!(reproduced.table, reseeded.table);
+        assert_eq!(reproduced.seed(), reseeded.seed());
+
+        // The backtracking search proves that there is no solution for a smaller table.
+        assert_eq!(
+            PhfMap::<'_, usize, { LEN - 1 }>::try_with_backtracking(KVS).err(),
+            Some(PhfBuildError::NoSolution),
+        );
+    }
+
+    #[test]
+    fn with_config() {
+        const KVS: &[(Key<'static>, usize)] = &[
+            (b"ERROR_1040_CODE", 1040),
+            (b"ERROR_1042_CODE", 1042),
+            (b"ERROR_1137_CODE", 1137),
+            (b"ERROR_2042", 2042),
+        ];
+
+        const TUNED: PhfConfig<'static> = PhfConfig::new().increments(&[1, 2]).max_tries(100);
+        const NO_LEN: PhfConfig<'static> = PhfConfig::new().include_len(false);
+        const MIDDLE: PhfConfig<'static> = PhfConfig::new().candidate_positions(&[6, 8, 9]);
+
+        const TUNED_MAP: PhfMap<'static, usize> = PhfMap::with_config(KVS, &TUNED);
+        const NO_LEN_MAP: PhfMap<'static, usize> = PhfMap::with_config(KVS, &NO_LEN);
+        const MIDDLE_MAP: PhfMap<'static, usize> = PhfMap::with_config(KVS, &MIDDLE);
+
+        assert_eq!(MIDDLE_MAP.sig.as_slice(), &[9]);
+        for map in &[TUNED_MAP, NO_LEN_MAP, MIDDLE_MAP] {
+            for (key, value) in KVS {
+                assert_eq!(map.get(key), Some(value));
+            }
+
+            assert_eq!(map.get(b"ERROR_1041_CODE"), None);
+        }
+
+        // A given keysig is used with the rest of the configuration.
+        const GIVEN: PhfMap<'static, usize> =
+            PhfMap::with_keysig_and_config(KVS, &[9], &TUNED.ignore_ascii_case(true));
+        assert_eq!(GIVEN.get(b"error_1137_code"), Some(&1137));
+        assert_eq!(GIVEN.get(b"error_2042"), Some(&2042));
+
+        // Position 10 is the same in the last two keys, so only their lengths tell them apart.
+        assert!(PhfMap::<'_, usize>::try_with_key_positions(KVS, "10").is_ok());
+        assert!(matches!(
+            PhfMap::<'_, usize>::try_with_key_positions_and_config(KVS, "10", &NO_LEN),
+            Err(PhfBuildError::NoUniqueKeysig { first: 1, second: 3, .. }),
+        ));
+
+        // Without the length, a key can only be told apart from its prefix by a position past the
+        // end of the prefix.
+        let prefixes: &[(Key<'_>, ())] = &[(b"ab", ()), (b"abc", ())];
+        let map = PhfMap::<'_, ()>::try_with_config(prefixes, &NO_LEN).unwrap();
+        assert_eq!(map.sig.as_slice(), &[2]);
+        assert_eq!(map.get(b"ab"), Some(&()));
+        assert_eq!(map.get(b"abc"), Some(&()));
+
+        // No table this dense works, so the random search can't find one either.
+        let dense = PhfConfig::new().max_sparsity(1);
+        assert_eq!(
+            PhfMap::<'_, usize>::try_with_config(KVS, &dense.backtracking(true)).err(),
+            Some(PhfBuildError::NoSolution),
+        );
+        assert!(matches!(
+            PhfMap::<'_, usize>::try_with_config(KVS, &dense),
+            Err(PhfBuildError::TableOverflow | PhfBuildError::SearchExhausted),
+        ));
+    }
+
+    #[test]
+    fn minimal() {
+        const KVS: &[(Key<'static>, usize)] = &[
+            (b"fn", 0),
+            (b"let", 1),
+            (b"mut", 2),
+            (b"if", 3),
+            (b"else", 4),
+            (b"match", 5),
+            (b"loop", 6),
+            (b"while", 7),
+            (b"for", 8),
+            (b"in", 9),
+        ];
+
+        const LEN: usize = crate::table_len_minimal(KVS);
+        const MAP: PhfMap<'static, usize, LEN> = PhfMap::new_minimal(KVS);
+
+        assert_eq!(LEN, KVS.len() + MAP.num_chars);
+        assert_eq!(MAP.max_hash(), KVS.len() - 1);
+
+        let mut seen = [false; 10];
+        for (key, value) in KVS {
+            assert_eq!(MAP.get(key), Some(value));
+
+            let hash = MAP.get_hash(key).unwrap();
+            assert!(!seen[hash]);
+            seen[hash] = true;
+        }
+
+        assert_eq!(MAP.get(b"f"), None);
+        assert_eq!(MAP.get_hash(b"lets"), None);
+
+        // With the length in the hash, the table starts at the length of the shortest key.
+        const DENSE: PhfConfig<'static> = PhfConfig::new().min_load_factor(75).backtracking(true);
+        const DENSE_MAP: PhfMap<'static, usize> = PhfMap::with_config(KVS, &DENSE);
+
+        assert!(KVS.len() * 100 >= 75 * (DENSE_MAP.max_hash() + 1));
+        for (key, value) in KVS {
+            assert_eq!(DENSE_MAP.get(key), Some(value));
+        }
+
+        assert_eq!(DENSE_MAP.get(b"f"), None);
+
+        // A load factor is always searched for by backtracking.
+        let random = PhfConfig::new().minimal().include_len(false);
+        let map = PhfMap::<'_, usize, LEN>::try_with_config(KVS, &random).unwrap();
+        assert_eq!(map.table, MAP.table);
+    }
+
+    #[test]
+    fn displacement() {
+        const KVS: &[(Key<'static>, usize)] = SQL_KEYWORDS;
+
+        const CONFIG: PhfConfig<'static> = PhfConfig::new().strategy(Strategy::Displacement);
+        const LEN: usize = crate::table_len_with_config(KVS, &CONFIG);
+        const MAP: PhfMap<'static, usize, LEN> = PhfMap::with_config(KVS, &CONFIG);
+
+        const MINIMAL: PhfConfig<'static> = CONFIG.minimal().ignore_ascii_case(true);
+        const MINIMAL_MAP: PhfMap<'static, usize> = PhfMap::with_config(KVS, &MINIMAL);
+
+        assert_eq!(MAP.max_hash(), 12);
+        assert_eq!(MINIMAL_MAP.max_hash(), 11);
+        for (key, value) in KVS {
+            assert_eq!(MAP.get(key), Some(value));
+            assert_eq!(MINIMAL_MAP.get(key), Some(value));
+            assert_eq!(MINIMAL_MAP.get(&key.to_ascii_uppercase()), Some(value));
+        }
+
+        assert_eq!(MAP.get(b"joins"), None);
+        assert_eq!(MAP.get(b""), None);
+        assert_eq!(MAP.iter_hash_order().count(), KVS.len());
+
+        let keys = random_words(10_000);
+        let kvs: Vec<(Key<'_>, u32)> = keys.iter().zip(0..).map(|(k, i)| (&k[..], i)).collect();
+
+        let map = PhfMap::<'_, u32, 32_768>::try_with_config(&kvs, &CONFIG).unwrap();
+        assert_eq!(map.table_len(), 11_111 * 2 + 2_500 * 2);
+        for (i, (key, value)) in kvs.iter().enumerate() {
+            assert_eq!(map.get_entry(key), Some((i, *key, value)));
+        }
+
+        assert_eq!(map.get(b"ab"), None);
+    }
+
+    #[test]
+    fn many_keys_in_const() {
+        // The keysig search, the random search and the checks before them must all be fast enough
+        // for const evaluation.
+        const MAP: PhfMap<'static, u32, 8192> = PhfMap::new(&RANDOM_KVS);
+
+        for (key, value) in &RANDOM_KVS {
+            assert_eq!(MAP.get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn small_stack() {
+        // The scratch space of the search is bounded by `TABLE`, or by a few kilobytes at most.
+        let thread = std::thread::Builder::new().stack_size(128 * 1024).spawn(|| {
+            let kvs: &[(Key<'_>, u32)] = &[(b"fn", 1), (b"let", 2), (b"mut", 3)];
+            PhfMap::<'_, u32, 64, 7>::try_new(kvs).is_ok()
+                && PhfMap::<'_, u32, 64, 7>::try_with_backtracking(kvs).is_ok()
+        });
+
+        assert!(thread.unwrap().join().unwrap());
+    }
+
+    #[test]
+    fn displacement_in_const() {
+        static KVS: [(Key<'static>, u32); 3000] = const_words();
+
+        // The check for duplicate keys and the search for pilots must both be fast enough for
+        // const evaluation.
+        const CONFIG: PhfConfig<'static> = PhfConfig::new().strategy(Strategy::Displacement);
+        const MAP: PhfMap<'static, u32, 8192> = PhfMap::with_config(&KVS, &CONFIG);
+
+        assert_eq!(MAP.table_len(), 3333 * 2 + 750 * 2);
+        for (key, value) in &KVS {
+            assert_eq!(MAP.get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn order_keywords_test() {
+        let keys = random_words(200);
+        let kvs: Vec<(Key<'_>, ())> = keys.iter().map(|key| (key.as_slice(), ())).collect();
+        let entries = Entries::Bytes(&kvs);
+        let sig = find_unique_keysig::<_, MAX_KEYSIG_LEN>(entries, 0, &PhfConfig::new()).unwrap();
+        let sig = sig.as_slice();
+
+        let char_freq = count_char_frequency(entries, sig, false);
+        let mut order = vec![0; kvs.len()];
+        order_keywords::<_, 200>(&mut order, entries, sig, false, &char_freq);
+
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert!(sorted.iter().copied().eq(0..kvs.len() as u16));
+
+        let score = |i: u16| freq_score(kvs[i as usize].0, sig, false, &char_freq);
+        assert!(order.iter().all(|&i| score(i) <= score(order[0])));
+
+        // A key that brings new characters comes after every key that doesn't need them.
+        let mut settled = [false; 256];
+        for (i, &idx) in order.iter().enumerate() {
+            if !is_keysig_settled(kvs[idx as usize].0, sig, false, &settled) {
+                assert!(!order[i..].iter().any(|&j| {
+                    is_keysig_settled(kvs[j as usize].0, sig, false, &settled)
+                }));
+            }
+
+            for_each_char_in_keysig!(kvs[idx as usize].0, sig, false, |c| {
+                settled[c as usize] = true;
+            });
+        }
+    }
+
+    #[test]
+    fn wide_slots() {
+        // More keys than fit in a `u8`.
+        let keys = random_words(300);
+        let kvs: Vec<(Key<'_>, usize)> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.as_slice(), i))
+            .collect();
+
+        let map: PhfMap<'_, usize, 8192> = PhfMap::try_new(&kvs).unwrap();
+        for (key, i) in &kvs {
+            assert_eq!(map.get(key).copied(), Some(*i));
+        }
+
+        assert_eq!(map.get(b"AB"), None);
+        assert_eq!(map.get(b"zzzzzzzzzzzzzzzzzzzz"), None);
+    }
+
+    #[test]
+    fn table_len() {
+        const KVS: &[(Key<'static>, usize)] = &[
+            (b"fn", 1),
+            (b"let", 2),
+            (b"mut", 3),
+            (b"loop", 4),
+            (b"while", 5),
+        ];
+
+        const PHF: PhfMap<'static, usize, { super::table_len(KVS) }> = PhfMap::new(KVS);
+
+        assert_eq!(PHF.table_len(), PHF.max_hash + 1 + PHF.num_chars);
+        assert_eq!(PHF.assoc_width, 1);
+        assert_eq!(std::mem::size_of_val(&PHF.table), PHF.table_len());
+        assert_eq!(PHF.get(b"loop").copied(), Some(4));
+        assert_eq!(PHF.get(b"for").copied(), None);
+    }
+
+    #[test]
+    fn table_len_for_each_constructor() {
+        const STRS: &[(&str, usize)] = &[("fn", 1), ("let", 2), ("mut", 3), ("loop", 4)];
+        const KVS: &[(Key<'static>, usize)] =
+            &[(b"fn", 1), (b"let", 2), (b"mut", 3), (b"loop", 4)];
+
+        const CONFIG: PhfConfig<'static> = PhfConfig::new().include_len(false);
+        const STR: PhfMap<'static, usize, { table_len_str(STRS) }> = PhfMap::new_str(STRS);
+        const LEN: PhfMap<'static, usize, { table_len_with_keysig_len(KVS, 2) }> =
+            PhfMap::with_keysig_len(KVS, 2);
+        const SIG: PhfMap<'static, usize, { table_len_with_keysig(KVS, &[0, -1], &CONFIG) }> =
+            PhfMap::with_keysig_and_config(KVS, &[0, -1], &CONFIG);
+        const POSITIONS_LEN: usize = table_len_with_key_positions(KVS, "1,$", &CONFIG);
+        const POSITIONS: PhfMap<'static, usize, POSITIONS_LEN> =
+            PhfMap::with_key_positions_and_config(KVS, "1,$", &CONFIG);
+
+        assert_eq!(STR.get_str("mut"), Some(&3));
+        assert_eq!(LEN.get(b"let"), Some(&2));
+        assert_eq!(SIG.get(b"loop"), Some(&4));
+        assert_eq!(POSITIONS.get(b"fn"), Some(&1));
+        assert_eq!(std::mem::size_of_val(&STR.table), STR.table_len());
+        assert_eq!(std::mem::size_of_val(&LEN.table), LEN.table_len());
+        assert_eq!(std::mem::size_of_val(&SIG.table), SIG.table_len());
+        assert_eq!(std::mem::size_of_val(&POSITIONS.table), POSITIONS.table_len());
+    }
+
+    #[test]
+    fn get_entry() {
+        const KVS: &[(Key<'static>, usize)] = &[
+            (b"fn", 1),
+            (b"let", 2),
+            (b"mut", 3),
+            (b"loop", 4),
+            (b"while", 5),
+        ];
+
+        const PHF: PhfMap<'static, usize> = PhfMap::new(KVS);
+
+        assert_eq!(PHF.get_index(b"while"), Some(4));
+        assert_eq!(PHF.get_index(b"whoo"), None);
+        assert_eq!(PHF.get_entry(b"fn"), Some((0, &b"fn"[..], &1)));
+        assert_eq!(PHF.get_entry(b"mut"), Some((2, &b"mut"[..], &3)));
+        assert_eq!(PHF.get_entry(b"tipe"), None);
+    }
+
+    #[test]
+    fn iter() {
+        const KVS: &[(Key<'static>, usize)] = &[
+            (b"fn", 1),
+            (b"let", 2),
+            (b"mut", 3),
+            (b"loop", 4),
+            (b"while", 5),
+        ];
+
+        const PHF: PhfMap<'static, usize> = PhfMap::new(KVS);
+
+        assert_eq!(PHF.len(), 5);
+        assert!(!PHF.is_empty());
+        assert!(PHF.keys().eq(KVS.iter().map(|kv| kv.0)));
+        assert!(PHF.values().eq(KVS.iter().map(|kv| &kv.1)));
+        assert!((&PHF).into_iter().eq(PHF.iter()));
+
+        let mut by_hash: Vec<_> = PHF.iter_hash_order().collect();
+        assert!(by_hash.windows(2).all(|w| PHF.hash(w[0].0) < PHF.hash(w[1].0)));
+
+        by_hash.sort_by_key(|&(_, &value)| value);
+        assert!(by_hash.into_iter().eq(PHF.iter()));
+    }
+
+    #[test]
+    fn str_keys() {
+        const PHF: PhfMap<'static, usize> = PhfMap::new_str(&[
+            ("select", 1),
+            ("from", 2),
+            ("where", 3),
+        ]);
+
+        assert_eq!(PHF.get_str("from").copied(), Some(2));
+        assert_eq!(PHF.get_str("join").copied(), None);
+        assert!(PHF.str_keys().eq(vec!["select", "from", "where"]));
+        assert_eq!(PHF.iter_str().last(), Some(("where", &3)));
+
+        let duplicate = PhfMap::<'_, ()>::try_new_str(&[("from", ()), ("join", ()), ("from", ())]);
+        assert_eq!(duplicate.err(), Some(PhfBuildError::DuplicateKeys { first: 0, second: 2 }));
+    }
+
+    #[test]
+    fn ignore_ascii_case() {
+        const PHF: PhfMap<'static, usize> = PhfMap::new_ignore_ascii_case(&[
+            (b"select", 1),
+            (b"FROM", 2),
+            (b"Where", 3),
+        ]);
+
+        assert_eq!(PHF.get(b"SELECT").copied(), Some(1));
+        assert_eq!(PHF.get(b"select").copied(), Some(1));
+        assert_eq!(PHF.get(b"SeLeCt").copied(), Some(1));
+        assert_eq!(PHF.get(b"from").copied(), Some(2));
+        assert_eq!(PHF.get(b"wHERE").copied(), Some(3));
+        assert_eq!(PHF.get(b"selects").copied(), None);
+
+        const DUPLICATE: Result<PhfMap<'static, ()>, PhfBuildError> =
+            PhfMap::try_new_ignore_ascii_case(&[(b"from", ()), (b"FROM", ())]);
+
+        assert_eq!(DUPLICATE.err(), Some(PhfBuildError::DuplicateKeys { first: 0, second: 1 }));
+    }
 }
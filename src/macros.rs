@@ -0,0 +1,61 @@
+/// Builds a `PhfMap` from string literal keys.
+///
+/// ```
+/// use const_phf::{phf_map, PhfMap};
+///
+/// const KEYWORDS: PhfMap<'static, usize> = phf_map! {
+///     "as" => 1,
+///     "break" => 2,
+///     "const" => 3,
+/// };
+///
+/// assert_eq!(KEYWORDS.get(b"break"), Some(&2));
+/// ```
+///
+/// The table has the default size. To size it to fit, give the same entries to `table_len_str`
+/// and build the map with `PhfMap::new_str`.
+///
+/// Duplicate keys are rejected at the site of the second occurrence:
+///
+/// ```compile_fail
+/// use const_phf::{phf_map, PhfMap};
+///
+/// const KEYWORDS: PhfMap<'static, usize> = phf_map! {
+///     "as" => 1,
+///     "as" => 2,
+/// };
+/// ```
+#[macro_export]
+macro_rules! phf_map {
+    ($($key:literal => $value:expr),* $(,)?) => {{
+        // A duplicate key is an unreachable pattern here, and denying that lint gives an error
+        // that points at the offending literal instead of at the whole map.
+        #[allow(dead_code)]
+        #[deny(unreachable_patterns)]
+        fn duplicate_keys(key: &str) {
+            match key {
+                $($key => {})*
+                _ => {}
+            }
+        }
+
+        $crate::PhfMap::new_str(&[$(($key, $value)),*])
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PhfMap;
+
+    #[test]
+    fn phf_map() {
+        const PHF: PhfMap<'static, u8> = phf_map! {
+            "if" => b'i',
+            "else" => b'e',
+            "match" => b'm',
+        };
+
+        assert_eq!(PHF.get(b"else").copied(), Some(b'e'));
+        assert_eq!(PHF.get(b"loop").copied(), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Finds a perfect hash for a keyword file and prints it, along with code that implements it.
+
+use std::ascii;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+use const_phf::PhfBuildError;
+use const_phf::codegen::{GperfFile, Layout};
+
+const USAGE: &str = "\
+usage: const-phf [options] [FILE]
+
+Reads keywords from FILE, or from standard input if FILE is missing or `-`. Each line holds a
+keyword, optionally followed by a comma and its value: an integer, character or string literal,
+or the name of a constant. Input in the format of a gperf file, with declarations and `%%`
+separators, is also accepted.
+
+options:
+    -k POSITIONS        use these key positions, in the syntax of gperf's `-k`
+    --ignore-case       compare keywords without regard to ASCII case
+    --emit KIND         print `summary` (the default), `rust` or `c`; may be repeated
+    --name NAME         name of the generated Rust module (default `keywords`)
+    --value-type TYPE   type of the values in the generated Rust module
+    -h, --help          print this message
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Emit {
+    Summary,
+    Rust,
+    C,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    path: Option<String>,
+    ignore_case: bool,
+    key_positions: Option<String>,
+    emit: Vec<Emit>,
+    name: String,
+    value_type: Option<String>,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut opts = Options {
+        path: None,
+        ignore_case: false,
+        key_positions: None,
+        emit: Vec::new(),
+        name: "keywords".to_owned(),
+        value_type: None,
+    };
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next().ok_or_else(|| format!("missing value for `{}`", name))
+        };
+
+        match arg.as_str() {
+            "-k" => opts.key_positions = Some(value("-k")?),
+            "--ignore-case" => opts.ignore_case = true,
+            "--name" => opts.name = value("--name")?,
+            "--value-type" => opts.value_type = Some(value("--value-type")?),
+            "--emit" => {
+                let kind = match value("--emit")?.as_str() {
+                    "summary" => Emit::Summary,
+                    "rust" => Emit::Rust,
+                    "c" => Emit::C,
+                    kind => return Err(format!("unknown `--emit` kind `{}`", kind)),
+                };
+
+                opts.emit.push(kind);
+            }
+
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("unknown option `{}`", arg));
+            }
+
+            _ if opts.path.is_none() => opts.path = Some(arg),
+            _ => return Err(format!("unexpected argument `{}`", arg)),
+        }
+    }
+
+    if opts.emit.is_empty() {
+        opts.emit.push(Emit::Summary);
+    }
+
+    Ok(opts)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(msg) => {
+            eprintln!("error: {}\n\n{}", msg, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(msg) = run(&opts) {
+        eprintln!("error: {}", msg);
+        process::exit(1);
+    }
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    let src = read_input(opts.path.as_deref()).map_err(|e| e.to_string())?;
+
+    // A keyword list is a gperf file without declarations.
+    let mut file = GperfFile::parse(&src).map_err(|e| e.to_string())?;
+
+    file.ignore_case |= opts.ignore_case;
+    if let Some(ref spec) = opts.key_positions {
+        file.set_key_positions(spec).map_err(|e| e.to_string())?;
+    }
+
+    let layout = file.layout().map_err(|e| describe_error(&file, e))?;
+
+    for emit in &opts.emit {
+        match emit {
+            Emit::Summary => print_summary(&file, &layout),
+            Emit::C => print!("{}", file.to_c().map_err(|e| e.to_string())?),
+            Emit::Rust => {
+                let has_values = file.keywords.iter().any(|kw| !kw.fields.is_empty());
+                let value_type = match opts.value_type {
+                    Some(ref ty) => ty.as_str(),
+                    None if !has_values => "()",
+                    None => return Err("keywords have values, but no `--value-type`".into()),
+                };
+
+                print!("{}", file.to_rust(&opts.name, value_type).map_err(|e| e.to_string())?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_input(path: Option<&str>) -> io::Result<String> {
+    match path {
+        None | Some("-") => {
+            let mut src = String::new();
+            io::stdin().read_to_string(&mut src)?;
+            Ok(src)
+        }
+
+        Some(path) => fs::read_to_string(path),
+    }
+}
+
+fn print_summary(file: &GperfFile, layout: &Layout) {
+    let sig: Vec<String> = layout.sig.iter().map(|idx| idx.to_string()).collect();
+
+    println!("keys: {}", file.keywords.len());
+    println!("keysig: [{}]", sig.join(", "));
+    println!("max hash: {}", layout.max_hash);
+    println!(
+        "fill ratio: {:.1}% ({} of {} slots)",
+        layout.fill_ratio() * 100.0,
+        layout.len(),
+        layout.table.len(),
+    );
+
+    let mut used = [false; 256];
+    for kw in &file.keywords {
+        for c in layout.keysig(&kw.key) {
+            used[c as usize] = true;
+        }
+    }
+
+    println!("assoc values:");
+    for c in (0..=u8::MAX).filter(|&c| used[c as usize]) {
+        println!("    {:<6}{}", escape(&[c]), layout.assoc_values[c as usize]);
+    }
+}
+
+/// Describes a failed search, naming the keywords responsible and where they appear.
+fn describe_error(file: &GperfFile, e: PhfBuildError) -> String {
+    let keyword = |i: usize| {
+        let kw = &file.keywords[i];
+        format!("`{}` (line {})", escape(&kw.key), kw.line)
+    };
+
+    match e {
+        PhfBuildError::DuplicateKeys { first, second } => {
+            format!("duplicate keys {} and {}", keyword(first), keyword(second))
+        }
+
+        PhfBuildError::NoUniqueKeysig { first, second, sig, truncated } => format!(
+            "keys {} and {} are indistinguishable by keysig {:?}{}",
+            keyword(first),
+            keyword(second),
+            sig.as_slice(),
+            if truncated { " and more positions" } else { "" },
+        ),
+
+        e => e.to_string(),
+    }
+}
+
+fn escape(s: &[u8]) -> String {
+    s.iter().flat_map(|&c| ascii::escape_default(c)).map(char::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn parse_args_test() {
+        let opts = parse_args(args("-k 1,$ --emit rust --emit c keywords.gperf")).unwrap();
+        assert_eq!(opts.key_positions.as_deref(), Some("1,$"));
+        assert_eq!(opts.emit, [Emit::Rust, Emit::C]);
+        assert_eq!(opts.path.as_deref(), Some("keywords.gperf"));
+
+        let opts = parse_args(args("--ignore-case -")).unwrap();
+        assert_eq!(opts.emit, [Emit::Summary]);
+        assert!(opts.ignore_case);
+
+        assert!(parse_args(args("--emit rs")).is_err());
+        assert!(parse_args(args("-k")).is_err());
+        assert!(parse_args(args("a b")).is_err());
+    }
+
+    #[test]
+    fn describe_error_test() {
+        let file = GperfFile::parse("ab\nif\nba\nif\n").unwrap();
+        let err = file.layout().unwrap_err();
+        assert_eq!(describe_error(&file, err), "duplicate keys `if` (line 2) and `if` (line 4)");
+
+        let mut file = GperfFile::parse("ab\nif\nac\n").unwrap();
+        file.set_key_positions("1").unwrap();
+        let err = file.layout().unwrap_err();
+        assert_eq!(
+            describe_error(&file, err),
+            "keys `ab` (line 1) and `ac` (line 3) are indistinguishable by keysig [0]",
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! An order-preserving minimal perfect hash, under which each key hashes to its position.
+//!
+//! This is the construction of Czech, Havas and Majewski (CHM). Two hashes of each key pick a pair
+//! of vertices in a graph, so that each key is an edge. If the graph has no cycles, each vertex can
+//! be given a value such that the values at either end of each edge sum to its position, modulo
+//! the number of keys. Otherwise, the search tries again with another seed.
+
+use crate::error::PhfBuildError;
+use crate::rng::hash_bytes;
+use crate::util::{Entries, Key, key_eq};
+use crate::{MAX_SMALL_KEYS, check_keys};
+
+/// The number of vertices per key, in hundredths.
+///
+/// Above 2, a random graph with this many vertices is acyclic with probability
+/// `sqrt((c - 2) / c)`, which is about 1 in 5 here.
+const VERTICES_PER_KEY: usize = 209;
+
+/// How many seeds the search tries before it gives up with `SearchExhausted`.
+const MAX_SEEDS: u64 = 100;
+
+/// Marks the end of a list of edges in `find_values`.
+const NONE: u32 = u32::MAX;
+
+/// The default size of the vertex table, enough for any map with 255 keys or fewer.
+pub const MAX_ORDERED_TABLE_LEN: usize = vertices_for(MAX_SMALL_KEYS);
+
+/// A minimal perfect hash map that preserves the order of its keys.
+///
+/// Unlike `PhfMap`, which hashes a few characters of each key, this hashes every byte, and the
+/// hash of each key is its position in the input slice. `TABLE` is the number of vertices, each of
+/// which takes two bytes. Use `ordered_table_len` to pick the smallest `TABLE` that fits:
+///
+/// ```
+/// use const_phf::{OrderedPhfMap, ordered_table_len};
+///
+/// const KVS: &[(&[u8], usize)] = &[(b"fn", 1), (b"let", 2), (b"mut", 3)];
+/// const MAP: OrderedPhfMap<'static, usize, { ordered_table_len(KVS) }> = OrderedPhfMap::new(KVS);
+///
+/// assert_eq!(MAP.hash(b"mut"), 2);
+/// ```
+///
+/// See `PhfMap` for the scratch space that the search needs.
+pub struct OrderedPhfMap<'a, T, const TABLE: usize = MAX_ORDERED_TABLE_LEN> {
+    keys: Entries<'a, T>,
+    ignore_case: bool,
+    seed: u64,
+
+    /// The number of vertices in the graph, at most `TABLE`.
+    vertices: usize,
+    values: [u16; TABLE],
+}
+
+impl<'a, T, const TABLE: usize> OrderedPhfMap<'a, T, TABLE> {
+    pub const fn new(kvs: &'a [(Key<'a>, T)]) -> Self {
+        match Self::try_new(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    /// Like `new`, but keys are compared without regard to ASCII case.
+    pub const fn new_ignore_ascii_case(kvs: &'a [(Key<'a>, T)]) -> Self {
+        match Self::try_new_ignore_ascii_case(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic(kvs),
+        }
+    }
+
+    /// Like `new`, but with string keys.
+    pub const fn new_str(kvs: &'a [(&'a str, T)]) -> Self {
+        match Self::try_new_str(kvs) {
+            Ok(map) => map,
+            Err(e) => e.panic_with(Entries::Str(kvs)),
+        }
+    }
+
+    pub const fn try_new(kvs: &'a [(Key<'a>, T)]) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Bytes(kvs), false)
+    }
+
+    /// Like `try_new`, but keys are compared without regard to ASCII case.
+    pub const fn try_new_ignore_ascii_case(
+        kvs: &'a [(Key<'a>, T)],
+    ) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Bytes(kvs), true)
+    }
+
+    /// Like `try_new`, but with string keys.
+    pub const fn try_new_str(kvs: &'a [(&'a str, T)]) -> Result<Self, PhfBuildError> {
+        Self::build(Entries::Str(kvs), false)
+    }
+
+    const fn build(kvs: Entries<'a, T>, ignore_case: bool) -> Result<Self, PhfBuildError> {
+        if let Err(e) = check_keys(kvs, ignore_case) {
+            return Err(e);
+        }
+
+        let vertices = vertices_for(kvs.len());
+        if vertices > TABLE {
+            return Err(PhfBuildError::TableOverflow);
+        }
+
+        let mut map = OrderedPhfMap {
+            keys: kvs,
+            ignore_case,
+            seed: 0,
+            vertices,
+            values: [0; TABLE],
+        };
+
+        while map.seed < MAX_SEEDS {
+            if map.find_values() {
+                return Ok(map);
+            }
+
+            map.seed += 1;
+        }
+
+        Err(PhfBuildError::SearchExhausted)
+    }
+
+    /// Assigns a value to each vertex under the current seed. Returns `false` if the graph has a
+    /// cycle, in which case no assignment exists.
+    const fn find_values(&mut self) -> bool {
+        let n = self.keys.len();
+
+        // The ends of each edge, and a union-find forest over the vertices to detect cycles.
+        let mut ends = [[0u32; TABLE]; 2];
+        let mut parent = [0u32; TABLE];
+        let mut v = 0;
+        while v < self.vertices {
+            parent[v] = v as u32;
+            v += 1;
+        }
+
+        iter_keys!((e, key) in self.keys => {
+            let (a, b) = self.vertices_of(key);
+            if a == b {
+                return false;
+            }
+
+            let (ra, rb) = (find_root(&mut parent, a), find_root(&mut parent, b));
+            if ra == rb {
+                return false;
+            }
+
+            parent[ra] = rb as u32;
+            ends[0][e] = a as u32;
+            ends[1][e] = b as u32;
+        });
+
+        // A list of the edges at each vertex. Each edge is in two lists, one for each end.
+        let mut head = [NONE; TABLE];
+        let mut next = [[NONE; TABLE]; 2];
+        let mut e = 0;
+        while e < n {
+            let mut side = 0;
+            while side < 2 {
+                let v = ends[side][e] as usize;
+                next[side][e] = head[v];
+                head[v] = (2 * e + side) as u32;
+                side += 1;
+            }
+
+            e += 1;
+        }
+
+        // Walk each tree from an arbitrary root, giving each vertex the value that makes the edge
+        // by which it was reached sum to the position of that edge's key. The union-find forest
+        // is no longer needed, so its space holds the stack.
+        let mut visited = [false; TABLE];
+        let stack = &mut parent;
+        let mut root = 0;
+        while root < self.vertices {
+            if visited[root] {
+                root += 1;
+                continue;
+            }
+
+            visited[root] = true;
+            self.values[root] = 0;
+            stack[0] = root as u32;
+            let mut len = 1;
+            while len > 0 {
+                len -= 1;
+                let u = stack[len] as usize;
+
+                let mut edge = head[u];
+                while edge != NONE {
+                    let (e, side) = ((edge >> 1) as usize, (edge & 1) as usize);
+                    edge = next[side][e];
+
+                    let other = ends[side ^ 1][e] as usize;
+                    if visited[other] {
+                        continue;
+                    }
+
+                    visited[other] = true;
+                    self.values[other] = ((e + n - self.values[u] as usize) % n) as u16;
+                    stack[len] = other as u32;
+                    len += 1;
+                }
+            }
+
+            root += 1;
+        }
+
+        true
+    }
+
+    /// Returns the vertices at either end of the edge for `key`.
+    const fn vertices_of(&self, key: Key<'_>) -> (usize, usize) {
+        let h = hash_bytes(key, self.ignore_case, self.seed);
+        let m = self.vertices as u64;
+        ((((h & 0xffff_ffff) * m) >> 32) as usize, (((h >> 32) * m) >> 32) as usize)
+    }
+
+    /// Returns the position that `key` would have in the input slice if it were in the map.
+    ///
+    /// This doesn't compare `key` with the stored key, so it is cheaper than `get_index`, but any
+    /// key that isn't in the map hashes to the position of some other key.
+    pub const fn hash(&self, key: Key<'_>) -> usize {
+        let n = self.keys.len();
+        if n == 0 {
+            return 0;
+        }
+
+        let (a, b) = self.vertices_of(key);
+        (self.values[a] as usize + self.values[b] as usize) % n
+    }
+
+    /// Returns the position of `key` in the input slice.
+    pub const fn get_index(&self, key: Key<'_>) -> Option<usize> {
+        if self.keys.is_empty() {
+            return None;
+        }
+
+        let idx = self.hash(key);
+        if !key_eq(key, self.keys.key(idx), self.ignore_case) {
+            return None;
+        }
+
+        Some(idx)
+    }
+
+    pub const fn get(&self, key: Key<'_>) -> Option<&T> {
+        match self.get_index(key) {
+            Some(idx) => Some(self.keys.value(idx)),
+            None => None,
+        }
+    }
+
+    /// Returns the position of `key` in the input slice, along with the stored key and value.
+    pub const fn get_entry(&self, key: Key<'_>) -> Option<(usize, Key<'a>, &'a T)> {
+        let keys = self.keys;
+        match self.get_index(key) {
+            Some(idx) => Some((idx, keys.key(idx), keys.value(idx))),
+            None => None,
+        }
+    }
+
+    pub const fn get_str(&self, key: &str) -> Option<&T> {
+        self.get(key.as_bytes())
+    }
+
+    pub const fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the seed under which the graph was acyclic.
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the number of vertices actually used.
+    ///
+    /// This is the smallest `TABLE` that can hold this map.
+    pub const fn table_len(&self) -> usize {
+        self.vertices
+    }
+
+    /// Iterates over the entries in the order they were given, which is also the order of their
+    /// hash values.
+    pub fn iter(&self) -> crate::Iter<'a, T> {
+        crate::Iter::new(self.keys)
+    }
+}
+
+/// Returns the smallest `TABLE` for which `OrderedPhfMap::new(kvs)` will succeed.
+pub const fn ordered_table_len<T>(kvs: &[(Key<'_>, T)]) -> usize {
+    vertices_for(kvs.len())
+}
+
+const fn vertices_for(keys: usize) -> usize {
+    keys * VERTICES_PER_KEY / 100 + 1
+}
+
+/// Returns the root of the tree containing `v`, halving the path to it along the way.
+const fn find_root<const N: usize>(parent: &mut [u32; N], mut v: usize) -> usize {
+    while parent[v] as usize != v {
+        parent[v] = parent[parent[v] as usize];
+        v = parent[v] as usize;
+    }
+
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered() {
+        const KVS: &[(Key<'static>, usize)] = crate::tests::SQL_KEYWORDS;
+
+        const MAP: OrderedPhfMap<'static, usize, { ordered_table_len(KVS) }> =
+            OrderedPhfMap::new(KVS);
+
+        for (i, (key, value)) in KVS.iter().enumerate() {
+            assert_eq!(MAP.hash(key), i);
+            assert_eq!(MAP.get_index(key), Some(i));
+            assert_eq!(MAP.get(key), Some(value));
+        }
+
+        assert_eq!(MAP.get(b"joins"), None);
+        assert_eq!(MAP.get(b""), None);
+        assert_eq!(MAP.get_entry(b"by"), Some((5, &b"by"[..], &5)));
+
+        assert!(MAP.iter().map(|(key, _)| key).eq(KVS.iter().map(|kv| kv.0)));
+
+        const FOLDED: OrderedPhfMap<'static, usize> = OrderedPhfMap::new_ignore_ascii_case(KVS);
+        assert_eq!(FOLDED.get(b"WHERE"), Some(&2));
+
+        let empty: &[(Key<'_>, ())] = &[];
+        assert_eq!(OrderedPhfMap::<'_, ()>::new(empty).get(b"a"), None);
+
+        assert_eq!(
+            OrderedPhfMap::<'_, usize, 4>::try_new(KVS).err(),
+            Some(PhfBuildError::TableOverflow),
+        );
+
+        let dup: &[(Key<'_>, ())] = &[(b"a", ()), (b"b", ()), (b"a", ())];
+        assert_eq!(
+            OrderedPhfMap::<'_, ()>::try_new(dup).err(),
+            Some(PhfBuildError::DuplicateKeys { first: 0, second: 2 }),
+        );
+    }
+
+    #[test]
+    fn many_keys() {
+        let keys = crate::tests::random_words(1000);
+        let kvs: Vec<(Key<'_>, u32)> = keys.iter().zip(0..).map(|(k, i)| (&k[..], i)).collect();
+
+        let map = OrderedPhfMap::<'_, u32, 4096>::try_new(&kvs).unwrap();
+        for (i, (key, value)) in kvs.iter().enumerate() {
+            assert_eq!(map.get_entry(key), Some((i, *key, value)));
+        }
+
+        // Every word has at least three letters.
+        assert_eq!(map.get(b"ab"), None);
+
+        // The graph has a cycle under every seed before the one reported.
+        let mut rebuilt = OrderedPhfMap::<'_, u32, 4096>::try_new(&kvs).unwrap();
+        for seed in 0..map.seed() {
+            rebuilt.seed = seed;
+            assert!(!rebuilt.find_values());
+        }
+
+        rebuilt.seed = map.seed();
+        assert!(rebuilt.find_values());
+        assert_eq!(rebuilt.values, map.values);
+    }
+
+    #[test]
+    fn many_keys_in_const() {
+        static KVS: [(Key<'static>, u32); 1000] = crate::tests::const_words();
+        const MAP: OrderedPhfMap<'static, u32, { ordered_table_len(&KVS) }> =
+            OrderedPhfMap::new(&KVS);
+
+        for (i, (key, value)) in KVS.iter().enumerate() {
+            assert_eq!(MAP.get_entry(key), Some((i, *key, value)));
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! A perfect hash set, for when only membership matters.
+
+use crate::config::PhfConfig;
+use crate::error::PhfBuildError;
+use crate::util::{Entries, Key};
+use crate::sig::MAX_KEYSIG_LEN;
+use crate::{Keysigs, MAX_TABLE_LEN, PhfMap, search_table_len};
+
+/// A perfect hash set of byte strings.
+///
+/// This is a `PhfMap` whose values are `()`, so no space is spent on a value column. `TABLE` has
+/// the same meaning as it does for `PhfMap`, and so does `SIG`.
+pub struct PhfSet<'a, const TABLE: usize = MAX_TABLE_LEN, const SIG: usize = MAX_KEYSIG_LEN> {
+    map: PhfMap<'a, (), TABLE, SIG>,
+}
+
+impl<'a, const TABLE: usize, const SIG: usize> PhfSet<'a, TABLE, SIG> {
+    pub const fn new(keys: &'a [Key<'a>]) -> Self {
+        Self::with_keysig_len(keys, 0)
+    }
+
+    pub const fn with_keysig_len(keys: &'a [Key<'a>], len: usize) -> Self {
+        match PhfMap::build(entries(keys), len, &PhfConfig::new()) {
+            Ok(map) => PhfSet { map },
+            Err(e) => e.panic_with(entries(keys)),
+        }
+    }
+
+    pub const fn with_keysig(keys: &'a [Key<'a>], sig: &[isize]) -> Self {
+        match PhfMap::build_with_keysig(entries(keys), sig, &PhfConfig::new()) {
+            Ok(map) => PhfSet { map },
+            Err(e) => e.panic_with(entries(keys)),
+        }
+    }
+
+    pub const fn with_key_positions(keys: &'a [Key<'a>], spec: &str) -> Self {
+        match PhfMap::build_with_key_positions(entries(keys), spec, &PhfConfig::new()) {
+            Ok(map) => PhfSet { map },
+            Err(e) => e.panic_with(entries(keys)),
+        }
+    }
+
+    pub const fn try_new(keys: &'a [Key<'a>]) -> Result<Self, PhfBuildError> {
+        match PhfMap::build(entries(keys), 0, &PhfConfig::new()) {
+            Ok(map) => Ok(PhfSet { map }),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub const fn contains(&self, key: Key<'_>) -> bool {
+        self.map.get_index(key).is_some()
+    }
+
+    /// Returns the position of `key` in the input slice.
+    pub const fn get_index(&self, key: Key<'_>) -> Option<usize> {
+        self.map.get_index(key)
+    }
+
+    pub const fn len(&self) -> usize {
+        self.map.keys.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of bytes actually used by the table.
+    pub const fn table_len(&self) -> usize {
+        self.map.table_len()
+    }
+
+    /// Iterates over the keys in the order they were given.
+    pub fn iter(&self) -> impl Iterator<Item = Key<'a>> + 'a {
+        self.map.keys()
+    }
+}
+
+/// Returns the smallest `TABLE` for which `PhfSet::new(keys)` will succeed, with the default `SIG`.
+pub const fn set_table_len(keys: &[Key<'_>]) -> usize {
+    set_table_len_with_keysig_len(keys, 0)
+}
+
+/// Returns the smallest `TABLE` for which `PhfSet::with_keysig_len(keys, len)` will succeed.
+pub const fn set_table_len_with_keysig_len(keys: &[Key<'_>], len: usize) -> usize {
+    search_table_len::<(), MAX_KEYSIG_LEN>(entries(keys), Keysigs::Search(len), &PhfConfig::new())
+}
+
+/// Returns the smallest `TABLE` for which `PhfSet::with_keysig(keys, sig)` will succeed.
+pub const fn set_table_len_with_keysig(keys: &[Key<'_>], sig: &[isize]) -> usize {
+    search_table_len::<(), MAX_KEYSIG_LEN>(entries(keys), Keysigs::Given(sig), &PhfConfig::new())
+}
+
+/// Returns the smallest `TABLE` for which `PhfSet::with_key_positions(keys, spec)` will succeed.
+pub const fn set_table_len_with_key_positions(keys: &[Key<'_>], spec: &str) -> usize {
+    let config = PhfConfig::new();
+    search_table_len::<(), MAX_KEYSIG_LEN>(entries(keys), Keysigs::Positions(spec), &config)
+}
+
+/// The keys of a set, with nothing for a value.
+const fn entries<'a>(keys: &'a [Key<'a>]) -> Entries<'a, ()> {
+    Entries::Keys { keys, value: &() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        const KEYS: &[Key<'static>] = &[
+            b"select",
+            b"from",
+            b"where",
+            b"group",
+            b"order",
+            b"by",
+            b"having",
+            b"limit",
+        ];
+
+        const SET: PhfSet<'static, { set_table_len(KEYS) }> = PhfSet::new(KEYS);
+
+        assert!(SET.contains(b"where"));
+        assert!(!SET.contains(b"wherever"));
+        assert_eq!(SET.get_index(b"order"), Some(4));
+        assert_eq!(SET.get_index(b"join"), None);
+        assert_eq!(SET.len(), KEYS.len());
+        assert!(SET.iter().eq(KEYS.iter().copied()));
+    }
+
+    #[test]
+    fn set_table_len_for_each_constructor() {
+        const KEYS: &[Key<'static>] = &[b"if", b"else", b"while", b"for"];
+
+        const LEN: PhfSet<'static, { set_table_len_with_keysig_len(KEYS, 2) }> =
+            PhfSet::with_keysig_len(KEYS, 2);
+        const SIG: PhfSet<'static, { set_table_len_with_keysig(KEYS, &[0, 1]) }> =
+            PhfSet::with_keysig(KEYS, &[0, 1]);
+        const POSITIONS: PhfSet<'static, { set_table_len_with_key_positions(KEYS, "1,2") }> =
+            PhfSet::with_key_positions(KEYS, "1,2");
+
+        assert!(LEN.contains(b"else") && SIG.contains(b"while") && POSITIONS.contains(b"for"));
+        assert_eq!(std::mem::size_of_val(&LEN.map.table), LEN.table_len());
+        assert_eq!(std::mem::size_of_val(&SIG.map.table), SIG.table_len());
+        assert_eq!(std::mem::size_of_val(&POSITIONS.map.table), POSITIONS.table_len());
+    }
+}
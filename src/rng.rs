@@ -0,0 +1,71 @@
+//! Pseudo-random numbers and seeded hashes that work in a `const fn`.
+
+/// The finalizer of SplitMix64.
+pub const fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// A seeded FNV-1a hash of every byte of `key`, folded to lowercase if `ignore_case` is set.
+pub const fn hash_bytes(key: &[u8], ignore_case: bool, seed: u64) -> u64 {
+    let mut h = 0xcbf29ce484222325 ^ mix(seed);
+    iter!(c in key => {
+        let c = if ignore_case { c.to_ascii_lowercase() } else { c };
+        h = (h ^ c as u64).wrapping_mul(0x100000001b3);
+    });
+
+    mix(h)
+}
+
+/// An xorshift64* generator.
+#[derive(Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator whose output is determined by `seed`.
+    pub const fn new(seed: u64) -> Self {
+        // The state must not be zero, or it stays zero forever.
+        Rng { state: mix(seed.wrapping_add(0x9e3779b97f4a7c15)) | 1 }
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// Returns a number in `0..n`, which must be at most `u32::MAX`.
+    pub const fn below(&mut self, n: usize) -> usize {
+        (((self.next_u64() >> 32) * n as u64) >> 32) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng() {
+        let mut a = Rng::new(0);
+        let mut b = Rng::new(0);
+        let mut c = Rng::new(1);
+
+        let mut counts = [0; 3];
+        for _ in 0..300 {
+            let x = a.next_u64();
+            assert_eq!(x, b.next_u64());
+            assert_ne!(x, c.next_u64());
+
+            counts[a.below(3)] += 1;
+            b.below(3);
+        }
+
+        assert!(counts.iter().all(|&n| n > 50), "{:?}", counts);
+    }
+}
@@ -1,70 +1,318 @@
 //! Find unique signatures (subsets of characters) for each key.
 
-use crate::util::{ControlFlow, Key};
-use crate::set::ByteMultiSet;
+use crate::arr::ConstArray;
+use crate::config::PhfConfig;
+use crate::error::{Keysig, PhfBuildError};
+use crate::rng::{hash_bytes, mix};
+use crate::util::{ControlFlow, Entries, Key, key_eq};
 
+/// The default capacity of a keysig.
 pub const MAX_KEYSIG_LEN: usize = 7;
 
-pub type Sig = crate::arr::ConstArray<isize, MAX_KEYSIG_LEN>;
-
-pub const fn keysig(key: Key<'_>, sig: &[isize]) -> ByteMultiSet {
-    let mut ret = ByteMultiSet::new();
+/// The positions of the characters that contribute to the hash, at most `N` of them.
+pub type Sig<const N: usize = MAX_KEYSIG_LEN> = ConstArray<isize, N>;
 
+/// Reports that the keys at `first` and `second` are the same at every position in `sig`.
+///
+/// Only the first `MAX_KEYSIG_LEN` positions fit in the error. If `sig` is longer, the rest are
+/// dropped and the error says so.
+pub const fn no_unique_keysig(first: usize, second: usize, sig: &[isize]) -> PhfBuildError {
+    let mut ret = Sig::new(0);
     iter!(idx in sig => {
-        if let Some(c) = index(key, idx) {
-            ret.insert(c);
+        if ret.len() == MAX_KEYSIG_LEN {
+            let sig = Keysig(ret);
+            return PhfBuildError::NoUniqueKeysig { first, second, sig, truncated: true };
         }
+
+        ret.push(idx);
     });
 
-    ret
+    PhfBuildError::NoUniqueKeysig { first, second, sig: Keysig(ret), truncated: false }
 }
 
-pub const fn index(key: Key<'_>, idx: isize) -> Option<u8> {
-    let abs_idx = idx.abs() as usize;
+/// Returns the character at `idx` in `key`, folded to lowercase if `ignore_case` is set.
+pub const fn index(key: Key<'_>, idx: isize, ignore_case: bool) -> Option<u8> {
+    let abs_idx = idx.unsigned_abs();
     if abs_idx >= key.len() {
         return None;
     }
 
-    if idx < 0 {
-        Some(key[key.len() - abs_idx])
+    let c = if idx < 0 {
+        key[key.len() - abs_idx]
     } else {
-        Some(key[abs_idx])
+        key[abs_idx]
+    };
+
+    if ignore_case {
+        Some(c.to_ascii_lowercase())
+    } else {
+        Some(c)
     }
 }
 
-pub const fn find_unique_keysig(keys: &[Key<'_>], start_len: usize) -> Result<Sig, ()> {
-    let possible_indexes = &[0, 1, 2, 3, -1, -2, -3];
+/// The most positions the search will consider, counting the ones it tries first.
+pub const MAX_CANDIDATES: usize = 24;
+
+/// The most keysigs `find_unique_keysig` checks before it falls back to `find_keysig_greedy`.
+///
+/// There are over half a million combinations of up to seven of `MAX_CANDIDATES` positions, which
+/// would take far too long to check during const evaluation.
+const MAX_KEYSIGS_CHECKED: usize = 10_000;
+
+/// Finds a keysig of at most `N` positions under which every key is unique.
+///
+/// The positions in `config`, near either end of the keys by default, are tried first. If no
+/// combination of those works, every position at which the keys differ is tried, so keys that only
+/// differ in the middle can still be told apart. If there are too many combinations of those to
+/// check, this settles for the keysig from `find_keysig_greedy`.
+///
+/// Fails with `KeysigTooLong` if `start_len` is more than `N`.
+pub const fn find_unique_keysig<T, const N: usize>(
+    kvs: Entries<'_, T>,
+    start_len: usize,
+    config: &PhfConfig<'_>,
+) -> Result<Sig<N>, PhfBuildError> {
+    if start_len > N {
+        return Err(PhfBuildError::KeysigTooLong);
+    }
+
+    let mut budget = MAX_KEYSIGS_CHECKED;
+    let possible_indexes = config.positions;
     let mut i = start_len;
-    while i < possible_indexes.len() {
-        if let Some(sig) = find_unique_keysig_with_len(possible_indexes, i, keys) {
-            return Ok(sig);
+    while i <= possible_indexes.len() && i <= N {
+        match find_unique_keysig_with_len(possible_indexes, i, kvs, config, &mut budget) {
+            Some(sig) => return Ok(sig),
+            None if budget == 0 => return find_keysig_greedy(kvs, config),
+            None => {}
         }
 
         i += 1;
     }
 
-    Err(())
+    // The keys only differ further in, so look at every position where they do. If two keys are
+    // the same at all of those, no keysig can tell them apart.
+    let candidates = candidate_positions(kvs, possible_indexes, config);
+    if let Some((first, second)) = find_identical_keys(kvs, candidates.as_slice(), config) {
+        return Err(no_unique_keysig(first, second, possible_indexes));
+    }
+
+    let mut i = if start_len > 1 { start_len } else { 1 };
+    while i <= N {
+        match find_unique_keysig_with_len(candidates.as_slice(), i, kvs, config, &mut budget) {
+            Some(sig) => return Ok(sig),
+            None if budget == 0 => return find_keysig_greedy(kvs, config),
+            None => {}
+        }
+
+        i += 1;
+    }
+
+    // Every combination of up to `N` positions failed, including the first `N` candidates.
+    // Report a collision under those.
+    let mut sig = Sig::new(0);
+    iter!(idx in candidates.as_slice() => {
+        if sig.len() == N {
+            break;
+        }
+
+        sig.push(idx);
+    });
+
+    match find_keysig_collision::<T, N>(kvs, sig.as_slice(), config) {
+        None => Ok(sig),
+        Some((first, second)) => {
+            Err(no_unique_keysig(first, second, sig.as_slice()))
+        }
+    }
 }
 
-const fn find_unique_keysig_with_len(
+/// Finds a keysig of at most `N` positions under which every key is unique, the way gperf does.
+///
+/// Starting from an empty keysig, this repeatedly adds whichever position leaves the fewest pairs
+/// of keys that can't be told apart, then removes any position that turns out not to be needed.
+/// Unlike `find_unique_keysig`, it doesn't try every combination of positions, so it stays fast
+/// for hundreds of keys, but the keysig it finds may be longer.
+pub const fn find_keysig_greedy<T, const N: usize>(
+    kvs: Entries<'_, T>,
+    config: &PhfConfig<'_>,
+) -> Result<Sig<N>, PhfBuildError> {
+    let candidates = candidate_positions(kvs, config.positions, config);
+
+    let mut sig = Sig::new(0);
+    let mut collisions = count_keysig_collisions::<T, N>(kvs, &[], config, usize::MAX);
+    while collisions > 0 && sig.len() < N {
+        let mut best = None;
+        iter!(idx in candidates.as_slice() => {
+            if contains(sig.as_slice(), idx) {
+                continue;
+            }
+
+            sig.push(idx);
+            let count =
+                count_keysig_collisions::<T, N>(kvs, sig.as_slice(), config, collisions);
+            sig.pop();
+
+            if count < collisions {
+                best = Some(idx);
+                collisions = count;
+            }
+        });
+
+        match best {
+            Some(idx) => sig.push(idx),
+            None => break,
+        }
+    }
+
+    if let Some((first, second)) = find_keysig_collision::<T, N>(kvs, sig.as_slice(), config) {
+        return Err(no_unique_keysig(first, second, sig.as_slice()));
+    }
+
+    // A position added early may have been made redundant by the ones added after it.
+    let mut i = sig.len();
+    while i > 0 {
+        i -= 1;
+
+        let idx = sig.as_slice()[i];
+        sig.remove(i);
+        if !is_keysig_unique::<T, N>(kvs, sig.as_slice(), config) {
+            sig.insert(i, idx);
+        }
+    }
+
+    Ok(sig)
+}
+
+/// Counts the pairs of keys that `sig` can't tell apart, stopping early once there are `limit`.
+const fn count_keysig_collisions<T, const N: usize>(
+    kvs: Entries<'_, T>,
+    sig: &[isize],
+    config: &PhfConfig<'_>,
+    limit: usize,
+) -> usize {
+    scan_collisions::<T, N>(kvs, Compare::Keysig(sig), config, limit).count
+}
+
+const fn contains(sig: &[isize], idx: isize) -> bool {
+    iter!(other in sig => {
+        if other == idx {
+            return true;
+        }
+    });
+
+    false
+}
+
+/// Returns the positions at which some keys have different characters, starting with those in
+/// `first` and continuing from the start of the keys.
+///
+/// A position is left out if it picks out the same character as an earlier one in every key, as
+/// `-1` and `9` do when every key is ten bytes long.
+const fn candidate_positions<T>(
+    kvs: Entries<'_, T>,
+    first: &[isize],
+    config: &PhfConfig<'_>,
+) -> ConstArray<isize, MAX_CANDIDATES> {
+    let mut ret = ConstArray::new(0);
+    iter!(idx in first => {
+        push_candidate(&mut ret, kvs, idx, config);
+    });
+
+    let mut max_len = 0;
+    iter_keys!(key in kvs => {
+        if key.len() > max_len {
+            max_len = key.len();
+        }
+    });
+
+    // Positions already in `first` are skipped by `push_candidate`.
+    let mut pos = 0;
+    while pos < max_len && ret.len() < MAX_CANDIDATES {
+        push_candidate(&mut ret, kvs, pos as isize, config);
+        pos += 1;
+    }
+
+    ret
+}
+
+const fn push_candidate<T>(
+    candidates: &mut ConstArray<isize, MAX_CANDIDATES>,
+    kvs: Entries<'_, T>,
+    idx: isize,
+    config: &PhfConfig<'_>,
+) {
+    if candidates.len() == MAX_CANDIDATES {
+        return;
+    }
+
+    let mut seen = None;
+    let mut varies = false;
+    iter_keys!(key in kvs => {
+        if let Some(c) = index(key, idx, config.ignore_case) {
+            match seen {
+                None => seen = Some(c),
+                Some(prev) if prev != c => varies = true,
+                Some(_) => {}
+            }
+        }
+    });
+
+    if !varies {
+        return;
+    }
+
+    iter!(other in candidates.as_slice() => {
+        if is_same_position(kvs, idx, other) {
+            return;
+        }
+    });
+
+    candidates.push(idx);
+}
+
+/// Returns `true` if `a` and `b` refer to the same byte of every key.
+const fn is_same_position<T>(kvs: Entries<'_, T>, a: isize, b: isize) -> bool {
+    iter_keys!(key in kvs => {
+        let len = key.len() as isize;
+        let a = if a < 0 { len + a } else { a };
+        let b = if b < 0 { len + b } else { b };
+        let a_in_key = a >= 0 && a < len;
+        let b_in_key = b >= 0 && b < len;
+
+        if a_in_key != b_in_key || (a_in_key && a != b) {
+            return false;
+        }
+    });
+
+    true
+}
+
+/// Checks combinations of `k` positions from `set`, at most `budget` of them, until one is unique.
+/// The number checked is subtracted from `budget`.
+const fn find_unique_keysig_with_len<T, const N: usize>(
     set: &[isize],
     k: usize,
-    keys: &[Key<'_>],
-) -> Option<Sig> {
+    kvs: Entries<'_, T>,
+    config: &PhfConfig<'_>,
+    budget: &mut usize,
+) -> Option<Sig<N>> {
     let mut keysig = Sig::new(0);
-    match _comb(set, k, 0, &mut keysig, keys) {
-        ControlFlow::Break(()) => Some(keysig),
-        ControlFlow::Continue => None,
+    match _comb(set, k, 0, &mut keysig, kvs, config, budget) {
+        ControlFlow::Break(true) => Some(keysig),
+        ControlFlow::Break(false) | ControlFlow::Continue => None,
     }
 }
 
-const fn _comb(
+/// Breaks with `true` once `chosen` is unique, or with `false` once `budget` runs out.
+const fn _comb<T, const N: usize>(
     set: &[isize],
     k: usize,
     i: usize,
-    chosen: &mut Sig,
-    keys: &[Key<'_>],
-) -> ControlFlow<()> {
+    chosen: &mut Sig<N>,
+    kvs: Entries<'_, T>,
+    config: &PhfConfig<'_>,
+    budget: &mut usize,
+) -> ControlFlow<bool> {
     // Not enough items remain to choose `k`.
     if k > set.len() - i {
         return ControlFlow::Continue;
@@ -72,8 +320,13 @@ const fn _comb(
 
     // All items have been chosen.
     if k == 0 {
-        if is_keysig_unique(keys, chosen.as_slice()) {
-            return ControlFlow::Break(());
+        if *budget == 0 {
+            return ControlFlow::Break(false);
+        }
+
+        *budget -= 1;
+        if is_keysig_unique::<T, N>(kvs, chosen.as_slice(), config) {
+            return ControlFlow::Break(true);
         } else {
             return ControlFlow::Continue;
         }
@@ -81,31 +334,391 @@ const fn _comb(
 
     // Recurse with the `i`-th element selected.
     chosen.push(set[i]);
-    ret!(_comb(set, k - 1, i + 1, chosen, keys));
+    ret!(_comb(set, k - 1, i + 1, chosen, kvs, config, budget));
 
     // Recurse with the `i`-th element *not* selected.
     chosen.pop();
-    ret!(_comb(set, k, i + 1, chosen, keys));
+    ret!(_comb(set, k, i + 1, chosen, kvs, config, budget));
 
     ControlFlow::Continue
 }
 
-pub const fn is_keysig_unique(keys: &[Key<'_>], sig: &[isize]) -> bool {
-    iter!((i, key) in keys => {
+pub const fn is_keysig_unique<T, const N: usize>(
+    kvs: Entries<'_, T>,
+    sig: &[isize],
+    config: &PhfConfig<'_>,
+) -> bool {
+    scan_collisions::<T, N>(kvs, Compare::Keysig(sig), config, 1).count == 0
+}
+
+/// Returns the indices of the first pair of keys whose keysigs are indistinguishable.
+pub const fn find_keysig_collision<T, const N: usize>(
+    kvs: Entries<'_, T>,
+    sig: &[isize],
+    config: &PhfConfig<'_>,
+) -> Option<(usize, usize)> {
+    scan_collisions::<T, N>(kvs, Compare::Keysig(sig), config, usize::MAX).first
+}
+
+/// Returns the indices of the first pair of keys that are equal, or equal but for ASCII case if
+/// `ignore_case` is set.
+pub const fn find_duplicate_keys<T>(
+    kvs: Entries<'_, T>,
+    ignore_case: bool,
+) -> Option<(usize, usize)> {
+    let config = PhfConfig::new().ignore_ascii_case(ignore_case);
+    scan_collisions::<T, MAX_KEYSIG_LEN>(kvs, Compare::Key, &config, usize::MAX).first
+}
+
+/// What `scan_collisions` tells keys apart by.
+#[derive(Clone, Copy)]
+enum Compare<'s> {
+    /// The length of a key, if that is part of the hash, and the characters at these positions.
+    Keysig(&'s [isize]),
+
+    /// The whole key.
+    Key,
+}
+
+impl Compare<'_> {
+    const fn eq<const N: usize>(self, a: Key<'_>, b: Key<'_>, config: &PhfConfig<'_>) -> bool {
+        match self {
+            Compare::Keysig(sig) => is_keysig_eq::<N>(a, b, sig, config),
+            Compare::Key => key_eq(a, b, config.ignore_case),
+        }
+    }
+
+    const fn fingerprint(self, key: Key<'_>, config: &PhfConfig<'_>) -> u64 {
+        match self {
+            Compare::Keysig(sig) => fingerprint(key, sig, config),
+            Compare::Key => hash_bytes(key, config.ignore_case, 0),
+        }
+    }
+}
+/// The pairs of keys that can't be told apart.
+#[derive(Clone, Copy)]
+struct Collisions {
+    count: usize,
+
+    /// The pair that comes first in the input, by the index of the first key and then the second.
+    first: Option<(usize, usize)>,
+}
+
+impl Collisions {
+    const fn add(&mut self, i: usize, j: usize) {
+        self.count += 1;
+        self.first = match self.first {
+            Some((first, second)) if first < i || (first == i && second < j) => self.first,
+            _ => Some((i, j)),
+        };
+    }
+}
+
+/// The number of entries in the hash table that `scan_collisions` fills, which takes 16KB.
+const SCAN_LEN: usize = 4096;
+
+/// Finds the pairs of keys that `compare` can't tell apart, stopping early once there are `limit`.
+///
+/// Comparing every pair of keys is quadratic, so instead each key gets a fingerprint of what is
+/// compared, such as its length and keysig, and goes in a hash table by its fingerprint. Only keys
+/// that are found there with the same fingerprint are compared, so the scan can stop at the first
+/// collision. If there are too many keys for the table, they are split into several passes by
+/// fingerprint.
+const fn scan_collisions<T, const N: usize>(
+    kvs: Entries<'_, T>,
+    compare: Compare<'_>,
+    config: &PhfConfig<'_>,
+    limit: usize,
+) -> Collisions {
+    // One more than the index of each key must fit in the low bits of its entry.
+    if kvs.len() > u16::MAX as usize {
+        return pairwise_collisions::<T, N>(kvs, compare, config, limit);
+    }
+
+    // Keys are split into passes so that each one fills about three quarters of the table.
+    let passes = kvs.len().div_ceil(SCAN_LEN / 4 * 3);
+
+    let mut ret = Collisions { count: 0, first: None };
+    let mut pass = 0;
+    while pass < passes {
+        // Each entry holds the high bits of the fingerprint of a key above one more than its
+        // index, so that zero marks an empty entry.
+        let mut table = [0u32; SCAN_LEN];
+        let mut len = 0;
+        iter_keys!((i, key) in kvs => {
+            let fp = compare.fingerprint(key, config);
+            if (fp >> 16) as usize % passes != pass {
+                continue;
+            }
+
+            // An unlucky distribution of fingerprints. Fall back to comparing every pair.
+            if len == SCAN_LEN / 8 * 7 {
+                return pairwise_collisions::<T, N>(kvs, compare, config, limit);
+            }
+
+            // Any earlier key with the same fingerprint is between here and the next empty entry.
+            let high = (fp >> 48) as u32;
+            let mut slot = fp as usize % SCAN_LEN;
+            while table[slot] != 0 {
+                let j = (table[slot] & u16::MAX as u32) as usize - 1;
+                if table[slot] >> 16 == high && compare.eq::<N>(kvs.key(j), key, config) {
+                    ret.add(j, i);
+                    if ret.count >= limit {
+                        return ret;
+                    }
+                }
+
+                slot = (slot + 1) % SCAN_LEN;
+            }
+
+            table[slot] = high << 16 | (i + 1) as u32;
+            len += 1;
+        });
+
+        pass += 1;
+    }
+
+    ret
+}
+
+/// Like `scan_collisions`, but compares every pair of keys.
+const fn pairwise_collisions<T, const N: usize>(
+    kvs: Entries<'_, T>,
+    compare: Compare<'_>,
+    config: &PhfConfig<'_>,
+    limit: usize,
+) -> Collisions {
+    let mut ret = Collisions { count: 0, first: None };
+    iter_keys!((i, key) in kvs => {
         let mut j = i + 1;
-        while j < keys.len() {
-            let other = keys[j];
-            if key.len() == other.len() && keysig(key, sig).eq(&keysig(other, sig)) {
-                return false;
+        while j < kvs.len() {
+            if compare.eq::<N>(key, kvs.key(j), config) {
+                ret.add(i, j);
+                if ret.count >= limit {
+                    return ret;
+                }
             }
 
             j += 1;
         }
     });
 
+    ret
+}
+
+const fn is_keysig_eq<const N: usize>(
+    a: Key<'_>,
+    b: Key<'_>,
+    sig: &[isize],
+    config: &PhfConfig<'_>,
+) -> bool {
+    if config.include_len && a.len() != b.len() {
+        return false;
+    }
+
+    let (a, len) = sorted_keysig::<N>(a, sig, config.ignore_case);
+    let (b, other_len) = sorted_keysig::<N>(b, sig, config.ignore_case);
+    if len != other_len {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < len {
+        if a[i] != b[i] {
+            return false;
+        }
+
+        i += 1;
+    }
+
     true
 }
 
+/// Returns the characters in the keysig of `key` in ascending order, and how many there are.
+///
+/// Two keys have the same keysig if these are the same, since the order of the positions in a
+/// keysig doesn't matter.
+const fn sorted_keysig<const N: usize>(
+    key: Key<'_>,
+    sig: &[isize],
+    ignore_case: bool,
+) -> ([u8; N], usize) {
+    let mut chars = [0; N];
+    let mut len = 0;
+    for_each_char_in_keysig!(key, sig, ignore_case, |c| {
+        let mut i = len;
+        while i > 0 && chars[i - 1] > c {
+            chars[i] = chars[i - 1];
+            i -= 1;
+        }
+
+        chars[i] = c;
+        len += 1;
+    });
+
+    (chars, len)
+}
+
+/// Hashes the characters in the keysig of `key`, and its length if that is part of the hash.
+///
+/// The hashes of the characters are summed, so the fingerprint doesn't depend on the order of the
+/// positions in `sig`, just as a keysig doesn't.
+const fn fingerprint(key: Key<'_>, sig: &[isize], config: &PhfConfig<'_>) -> u64 {
+    let mut ret = if config.include_len { mix(key.len() as u64) } else { 0 };
+    iter!(idx in sig => {
+        if let Some(c) = index(key, idx, config.ignore_case) {
+            ret = ret.wrapping_add(mix(c as u64 | 0x100));
+        }
+    });
+
+    mix(ret)
+}
+
+/// Parses key positions written like the argument to gperf's `-k` option, such as `1,3-5,$`.
+///
+/// gperf counts positions from 1 and uses `$` for the last character, so these become positions
+/// counted from 0 and -1 respectively. `*` selects every position in a key of `max_key_len`
+/// characters. A position that is selected more than once is only included once.
+pub const fn parse_key_positions<const N: usize>(
+    spec: &[u8],
+    max_key_len: usize,
+) -> Result<Sig<N>, PhfBuildError> {
+    let mut sig = Sig::new(0);
+    let mut i = 0;
+    loop {
+        if i < spec.len() && spec[i] == b'$' {
+            i += 1;
+            if let Err(e) = push_position(&mut sig, -1) {
+                return Err(e);
+            }
+        } else if i < spec.len() && spec[i] == b'*' {
+            i += 1;
+            let mut pos = 0;
+            while pos < max_key_len {
+                if let Err(e) = push_position(&mut sig, pos as isize) {
+                    return Err(e);
+                }
+
+                pos += 1;
+            }
+        } else {
+            let (first, next) = match parse_position(spec, i) {
+                Some(x) => x,
+                None => return Err(PhfBuildError::InvalidKeyPositions { offset: i }),
+            };
+            i = next;
+
+            let mut last = first;
+            if i < spec.len() && spec[i] == b'-' {
+                i += 1;
+                match parse_position(spec, i) {
+                    Some((n, next)) if n >= first => {
+                        last = n;
+                        i = next;
+                    }
+
+                    _ => return Err(PhfBuildError::InvalidKeyPositions { offset: i }),
+                }
+            }
+
+            let mut pos = first;
+            while pos <= last {
+                if let Err(e) = push_position(&mut sig, pos as isize - 1) {
+                    return Err(e);
+                }
+
+                pos += 1;
+            }
+        }
+
+        if i == spec.len() {
+            return Ok(sig);
+        }
+
+        if spec[i] != b',' {
+            return Err(PhfBuildError::InvalidKeyPositions { offset: i });
+        }
+
+        i += 1;
+    }
+}
+
+/// Parses a 1-based position starting at `i`, returning it and the offset of the byte after it.
+const fn parse_position(spec: &[u8], mut i: usize) -> Option<(usize, usize)> {
+    // Anything longer can't be a position in a key whose characters fit in a `u16` hash.
+    const MAX_POSITION: usize = u16::MAX as usize;
+
+    let start = i;
+    let mut n = 0;
+    while i < spec.len() && spec[i].is_ascii_digit() {
+        n = n * 10 + (spec[i] - b'0') as usize;
+        if n > MAX_POSITION {
+            return None;
+        }
+
+        i += 1;
+    }
+
+    if i == start || n == 0 {
+        return None;
+    }
+
+    Some((n, i))
+}
+
+const fn push_position<const N: usize>(sig: &mut Sig<N>, idx: isize) -> Result<(), PhfBuildError> {
+    if contains(sig.as_slice(), idx) {
+        return Ok(());
+    }
+
+    if sig.len() == N {
+        return Err(PhfBuildError::KeysigTooLong);
+    }
+
+    sig.push(idx);
+    Ok(())
+}
+
+/// Returns the indices of the first pair of keys with the same length and the same character at
+/// each of `positions`.
+///
+/// If the length isn't part of the hash, keys of different lengths count as identical only if
+/// each of `positions` is past the end of both keys or within both.
+///
+/// Unlike `find_keysig_collision`, this compares position by position, so `positions` may be
+/// longer than a keysig.
+const fn find_identical_keys<T>(
+    kvs: Entries<'_, T>,
+    positions: &[isize],
+    config: &PhfConfig<'_>,
+) -> Option<(usize, usize)> {
+    iter_keys!((i, key) in kvs => {
+        let mut j = i + 1;
+        'others: while j < kvs.len() {
+            let other = kvs.key(j);
+            j += 1;
+
+            if config.include_len && key.len() != other.len() {
+                continue;
+            }
+
+            iter!(idx in positions => {
+                let a = index(key, idx, config.ignore_case);
+                let b = index(other, idx, config.ignore_case);
+                match (a, b) {
+                    (Some(a), Some(b)) if a != b => continue 'others,
+                    (Some(_), None) | (None, Some(_)) if !config.include_len => continue 'others,
+                    _ => {}
+                }
+            });
+
+            return Some((i, j - 1));
+        }
+    });
+
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,6 +782,80 @@ mod tests {
             b"try",
         ];
 
-        assert!(find_unique_keysig(keys, 0).is_ok());
+        let kvs = Entries::Keys { keys, value: &() };
+        assert!(find_unique_keysig::<_, MAX_KEYSIG_LEN>(kvs, 0, &PhfConfig::new()).is_ok());
+    }
+
+    #[test]
+    fn too_many_combinations() {
+        // Each key differs from the first at a position of its own, so it takes all 24 of them
+        // to tell the keys apart. Rather than checking every combination of up to 7, the search
+        // gives up and reports the collision that the greedy search finds.
+        let keys: Vec<Vec<u8>> = (0..25)
+            .map(|i| {
+                let mut key = vec![b'a'; 30];
+                key[i + 1] = b'a' + i as u8;
+
+                key
+            })
+            .collect();
+
+        let keys: Vec<Key<'_>> = keys.iter().map(|key| key.as_slice()).collect();
+        let kvs = Entries::Keys { keys: &keys, value: &() };
+        let config = PhfConfig::new();
+
+        let greedy = find_keysig_greedy::<_, MAX_KEYSIG_LEN>(kvs, &config);
+        assert!(matches!(greedy, Err(PhfBuildError::NoUniqueKeysig { .. })));
+        assert_eq!(find_unique_keysig::<_, MAX_KEYSIG_LEN>(kvs, 0, &config), greedy);
+
+        // Within the budget, the search still finds the smallest keysig.
+        let keys = &keys[..4];
+        let kvs = Entries::Keys { keys, value: &() };
+        let sig = find_unique_keysig::<_, MAX_KEYSIG_LEN>(kvs, 0, &config).unwrap();
+        assert_eq!(sig.as_slice(), [2, 3, 4]);
+    }
+
+    #[test]
+    fn many_keys() {
+        const KVS: &[(Key<'static>, u32)] = &crate::tests::RANDOM_KVS;
+
+        // Comparing every pair of keys for each candidate keysig is too slow for const evaluation.
+        const SIG: Result<Sig, PhfBuildError> =
+            find_unique_keysig(Entries::Bytes(KVS), 0, &PhfConfig::new());
+        let sig = SIG.unwrap();
+
+        let compares = [
+            Compare::Keysig(&[0]),
+            Compare::Keysig(&[0, -1]),
+            Compare::Keysig(&[1, 2]),
+            Compare::Keysig(sig.as_slice()),
+            Compare::Key,
+        ];
+        let config = PhfConfig::new().ignore_ascii_case(true);
+        let kvs = Entries::Bytes(KVS);
+        for compare in compares {
+            let scan = scan_collisions::<_, MAX_KEYSIG_LEN>(kvs, compare, &config, usize::MAX);
+            let pairwise =
+                pairwise_collisions::<_, MAX_KEYSIG_LEN>(kvs, compare, &config, usize::MAX);
+
+            assert_eq!(scan.count, pairwise.count);
+            assert_eq!(scan.first, pairwise.first);
+        }
+    }
+
+    #[test]
+    fn parse_key_positions_test() {
+        let parse = |spec: &str| parse_key_positions::<MAX_KEYSIG_LEN>(spec.as_bytes(), 4);
+
+        assert_eq!(parse("1,3,$").unwrap().as_slice(), &[0, 2, -1]);
+        assert_eq!(parse("2-4,3,$,$").unwrap().as_slice(), &[1, 2, 3, -1]);
+        assert_eq!(parse("*").unwrap().as_slice(), &[0, 1, 2, 3]);
+        assert_eq!(parse("1-8"), Err(PhfBuildError::KeysigTooLong));
+
+        assert_eq!(parse(""), Err(PhfBuildError::InvalidKeyPositions { offset: 0 }));
+        assert_eq!(parse("0"), Err(PhfBuildError::InvalidKeyPositions { offset: 0 }));
+        assert_eq!(parse("1,"), Err(PhfBuildError::InvalidKeyPositions { offset: 2 }));
+        assert_eq!(parse("3-1"), Err(PhfBuildError::InvalidKeyPositions { offset: 2 }));
+        assert_eq!(parse("1;2"), Err(PhfBuildError::InvalidKeyPositions { offset: 1 }));
     }
 }
@@ -22,13 +22,14 @@ macro_rules! iter {
 
 pub type Key<'a> = &'a [u8];
 
-pub const fn key_eq(a: Key<'_>, b: Key<'_>) -> bool {
+pub const fn key_eq(a: Key<'_>, b: Key<'_>, ignore_case: bool) -> bool {
     if a.len() != b.len() {
         return false;
     }
 
     iter!((i, a) in a => {
-        if a != b[i] {
+        let b = b[i];
+        if a != b && !(ignore_case && a.eq_ignore_ascii_case(&b)) {
             return false;
         }
     });
@@ -36,42 +37,130 @@ pub const fn key_eq(a: Key<'_>, b: Key<'_>) -> bool {
     true
 }
 
-pub const fn swap<T: Copy>(a: &mut T, b: &mut T) {
-    let tmp = *a;
-    *a = *b;
-    *b = tmp;
+/// The entries of a map, however they were given.
+///
+/// Keys may be byte strings or strings, and a set has keys alone. Each is read through its own
+/// variant, rather than by reinterpreting one slice as another.
+pub enum Entries<'a, T> {
+    Bytes(&'a [(Key<'a>, T)]),
+    Str(&'a [(&'a str, T)]),
+
+    /// Keys that all share one value.
+    Keys { keys: &'a [Key<'a>], value: &'a T },
 }
 
-pub const fn slice_swap<T: Copy>(s: &mut [T], a: usize, b: usize) {
-    let tmp = s[a];
-    s[a] = s[b];
-    s[b] = tmp;
+impl<T> Clone for Entries<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
-macro_rules! expect {
-    ($expr:expr, $msg:literal) => {
-        match $expr {
-            Some(x) => x,
-            None => panic!($msg),
+impl<T> Copy for Entries<'_, T> {}
+
+impl<'a, T> Entries<'a, T> {
+    pub const fn len(&self) -> usize {
+        match *self {
+            Entries::Bytes(kvs) => kvs.len(),
+            Entries::Str(kvs) => kvs.len(),
+            Entries::Keys { keys, .. } => keys.len(),
         }
-    };
+    }
 
-    ($expr:expr) => {
-        expect!($expr, "`expect` called on `Option::None`")
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub const fn key(&self, i: usize) -> Key<'a> {
+        match *self {
+            Entries::Bytes(kvs) => kvs[i].0,
+            Entries::Str(kvs) => kvs[i].0.as_bytes(),
+            Entries::Keys { keys, .. } => keys[i],
+        }
+    }
+
+    pub const fn value(&self, i: usize) -> &'a T {
+        match *self {
+            Entries::Bytes(kvs) => &kvs[i].1,
+            Entries::Str(kvs) => &kvs[i].1,
+            Entries::Keys { keys, value } => {
+                assert!(i < keys.len());
+                value
+            }
+        }
     }
 }
 
-macro_rules! expect_ok {
-    ($expr:expr, $msg:literal) => {
-        match $expr {
-            Ok(x) => x,
-            Err(_) => panic!($msg),
+/// Like `iter!`, but over the keys of an `Entries`.
+macro_rules! iter_keys {
+    (($idx:ident, $key:ident) in $entries:expr => $block:block) => {
+        {
+            let entries = $entries;
+            let mut i = 0;
+            while i < entries.len() {
+                let $key = entries.key(i);
+                #[allow(unused)]
+                let $idx = i;
+                i += 1;
+
+                $block
+            }
         }
     };
 
-    ($expr:expr) => {
-        expect_ok!($expr, "`expect` called on `Result::Err`")
-    }
+    ($key:ident in $entries:expr => $block:block) => {
+        iter_keys!((_i, $key) in $entries => $block);
+    };
+}
+
+pub const fn slice_swap<T: Copy>(s: &mut [T], a: usize, b: usize) {
+    let tmp = s[a];
+    s[a] = s[b];
+    s[b] = tmp;
+}
+
+/// Sorts a slice in ascending order of the key computed by a closure-like expression.
+///
+/// This is a heapsort, which needs neither recursion nor allocation, so it works in a `const fn`.
+/// The key must be `Copy` and comparable with `<`, and is recomputed for every comparison.
+macro_rules! sort_by_key {
+    ($slice:expr, |$elem:ident| $key:expr) => {
+        {
+            let s = &mut *$slice;
+            let len = s.len();
+
+            // The first `len / 2` steps build a max-heap, and the rest move its root to the end.
+            let mut i = len + len / 2;
+            while i > 0 {
+                i -= 1;
+                let (mut root, end) = if i >= len {
+                    (i - len, len)
+                } else {
+                    crate::util::slice_swap(s, 0, i);
+                    (0, i)
+                };
+
+                loop {
+                    let mut child = 2 * root + 1;
+                    if child >= end {
+                        break;
+                    }
+
+                    if child + 1 < end
+                        && { let $elem = s[child]; $key } < { let $elem = s[child + 1]; $key }
+                    {
+                        child += 1;
+                    }
+
+                    if { let $elem = s[root]; $key } >= { let $elem = s[child]; $key } {
+                        break;
+                    }
+
+                    crate::util::slice_swap(s, root, child);
+                    root = child;
+                }
+            }
+        }
+    };
 }
 
 #[derive(Clone, Copy)]
@@ -90,11 +179,11 @@ macro_rules! ret {
 }
 
 macro_rules! for_each_char_in_keysig {
-    ($key:expr, $sig:expr, |$c:ident| $block:block) => {
+    ($key:expr, $sig:expr, $ignore_case:expr, |$c:ident| $block:block) => {
         {
-            let ref key = $key;
+            let key = $key;
             iter!(idx in $sig => {
-                if let Some($c) = crate::sig::index(key, idx) {
+                if let Some($c) = crate::sig::index(key, idx, $ignore_case) {
                     $block
                 }
             });
@@ -104,8 +193,6 @@ macro_rules! for_each_char_in_keysig {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     #[test]
     fn sort_test() {
         let mut arr = [3, 1, 2, 0];